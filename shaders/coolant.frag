@@ -0,0 +1,21 @@
+#version 330 core
+
+in vec2 uv;
+
+out vec4 FragColor;
+
+// Coolant grid: x = temperature above ambient
+uniform sampler2D tex;
+uniform vec2 resolution;
+
+// Temperature above ambient at full glow
+const float FULL_GLOW = 200.0;
+
+void main() {
+    // grid.vert flips v, but the coolant grid is stored bottom-up like the world
+    float excess = texture(tex, vec2(uv.x, 1.0 - uv.y)).x;
+
+    // Hotter coolant glows brighter
+    float glow = clamp(excess / FULL_GLOW, 0, 1);
+    FragColor = vec4(vec3(1.0, 0.3, 0.0) * 0.5 * glow, 1.0);
+}
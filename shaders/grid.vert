@@ -1,4 +1,4 @@
-#version 440 core
+#version 330 core
 
 layout (location = 0) in vec3 aPos;
 
@@ -1,4 +1,4 @@
-#version 440 core
+#version 330 core
 out vec4 FragColor;
 void main() {
     FragColor = vec4(1.0, 1.0, 1.0, 1.0);
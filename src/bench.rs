@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+use simulation::particles::{create_simulation, Simulatable};
+use simulation::storage::ParticleStorage;
+use simulation::boundaries::{Boundary, create_boundaries};
+
+// Particle counts to benchmark
+const BENCH_SIZES: [usize; 2] = [100_000, 1_000_000];
+
+// Neutron spacing on the starting lattice (a bit more than a diameter apart)
+const BENCH_SPACING: f32 = 9.0;
+
+// Headless step-time benchmark (run with --bench, no window needed)
+//
+// Results (release build, single core). Moving particle storage to struct-of-arrays, before -> after:
+//
+//     100k particles:  integrate 0.216 -> 0.102 ms, narrowphase 6.68 -> 4.89 ms, full step 606 -> 522 ms
+//     1M particles:    integrate 3.05 -> 1.30 ms,   narrowphase 105 -> 75 ms,    full step 8.84 -> 7.35 s
+//
+// Since the lattice is walled in (so every particle is still there for the full step):
+//
+//     100k particles:  integrate 0.108 ms, narrowphase 8.16 ms, full step 555 ms
+//     1M particles:    integrate 1.35 ms,  narrowphase 117 ms,  full step 7.73 s
+pub fn run() {
+    for &n in BENCH_SIZES.iter() {
+        // Step 1: Fill a walled square lattice with neutrons moving in random directions
+        // (the default world is screen-sized, so open edges would despawn most of the lattice)
+        let mut sim = create_simulation(crate::SIM_SEED);
+        let neutron = sim.find_material("neutron").expect("Built-in registry has no \"neutron\" material");
+        let (radius, mass) = (sim.material(neutron).radius, sim.material(neutron).mass);
+        let side = (n as f32).sqrt().ceil() as usize;
+        let extent = (side as f32 - 0.5) * BENCH_SPACING;
+        sim.set_boundaries(create_boundaries((-0.5 * BENCH_SPACING, -0.5 * BENCH_SPACING), (extent, extent), Boundary::Wall));
+        for k in 0..n {
+            let position = ((k % side) as f32 * BENCH_SPACING, (k / side) as f32 * BENCH_SPACING);
+            let momentum = sim.random_momentum(crate::N_MOMENTUM);
+            sim.add_particle_with_momentum(neutron, position, radius, mass, momentum);
+        }
+        let dt = sim.substep_dt();
+
+        // Step 2: Time each stage on its own
+        let integrate = time_average(20, || sim.integrate(dt));
+        let pairs = sim.find_pairs();
+        let narrowphase = time_average(5, || sim.resolve_collisions(&pairs));
+        let step = time_average(2, || sim.advance());
+
+        println!("{:>9} particles ({} pairs, {} particles after): integrate {:>10.3} ms, narrowphase {:>10.3} ms, full step {:>10.3} ms",
+            n, pairs.len(), sim.particles.len(), millis(integrate), millis(narrowphase), millis(step));
+    }
+}
+
+// Helper function to time the average of runs calls
+fn time_average<F: FnMut()>(runs: u32, mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..runs {
+        f();
+    }
+    return start.elapsed() / runs;
+}
+
+// Helper function to convert a duration to fractional milliseconds
+fn millis(duration: Duration) -> f64 {
+    return duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0;
+}
@@ -0,0 +1,37 @@
+extern crate glfw;
+
+use glfw::ffi::glfwGetTime;
+
+// Frame clock for the windowed app (backed by GLFW's timer)
+pub struct Clock {
+    t: f64,
+}
+
+pub trait Timer {
+    // Advance to the current time, returning the elapsed dt (in sec)
+    fn tick(&mut self) -> f32;
+
+    // Time of the last tick (in sec)
+    fn now(&self) -> f64;
+}
+
+// Create a clock starting at the current time
+// (GLFW must already be initialized)
+pub fn create_clock() -> Clock {
+    return Clock {
+        t: unsafe { glfwGetTime() as f64 },
+    }
+}
+
+impl Timer for Clock {
+    fn tick(&mut self) -> f32 {
+        let t = unsafe { glfwGetTime() as f64 };
+        let dt = (t - self.t) as f32;
+        self.t = t;
+        return dt;
+    }
+
+    fn now(&self) -> f64 {
+        return self.t;
+    }
+}
@@ -1,27 +1,70 @@
 mod rendering;
-use glfw::ffi::glfwGetTime;
 use rendering::shapes::circle::{DrawCircle, create_circle_renderer};
+use rendering::shapes::line::{DrawLine, create_line_renderer};
+use rendering::general::{RenderGrid, make_grid_renderer};
 
 mod simulation;
 
 mod window;
 
+mod clock;
+use clock::Timer;
+
+mod bench;
+
+use std::fs;
+
 extern crate glfw;
 use glfw::{Context, Key, Action, GlfwReceiver};
-use simulation::particles::{create_simulation, Simulatable, Simulation, ParticleType};
+use simulation::particles::{create_simulation, Simulatable, Simulation, ParticleType, AMBIENT_TEMPERATURE};
+use simulation::storage::ParticleStorage;
+use simulation::boundaries::{Boundary, create_boundaries};
+use simulation::colliders::{Collider, outline};
+use simulation::neutrons::EnergyGroup;
+use simulation::materials::load_materials;
+use simulation::lineage::{to_dot, to_json};
+use simulation::coupling::{FluidSolver, HeatMap, Pump};
+use simulation::cpu_fluid::create_cpu_fluid;
+use simulation::fluid;
+use rendering::textures;
 
 extern crate gl;
 
+extern crate rand;
+
+extern crate rayon;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+
 // Settings
 const SCR_WIDTH: u32 = 1920;
 const SCR_HEIGHT: u32 = 1080;
 const SCR_TITLE: &'static str = "supernova";
-const N_RADIUS: f32 = 4.0;
-const FISSILE_RADIUS: f32 = 16.0;
-const N_MOMENTUM: f32 = 100.0;
+const WORLD_WIDTH: f32 = 1920.0;
+const WORLD_HEIGHT: f32 = 1080.0;
+const MATERIALS_PATH: &'static str = "materials.toml";
+const LINEAGE_DOT_PATH: &'static str = "lineage.dot";
+const LINEAGE_JSON_PATH: &'static str = "lineage.json";
+const ROD_SPEED: f32 = 200.0;
+const N_MOMENTUM: f32 = 12000.0;
+const SIM_SEED: u64 = 0;
+const COLLIDER_LINE_WIDTH: f32 = 2.0;
+const GLOW_COLOR: (f32, f32, f32) = (1.0, 0.3, 0.0);
+const GLOW_TEMPERATURE: f32 = 1000.0;    // Degrees above ambient at full glow
+const COOLANT_PUMP_FORCE: f32 = 1.0e6;  // Upward push on the coolant at the bottom of the vessel
+const CPU_FLUID_CELL_SIZE: u32 = 8;     // Screen pixels per cell of the CPU coolant grid
 
 // Entrypoint
 pub fn main() {
+    // Headless benchmark?
+    if std::env::args().any(|arg| arg == "--bench") {
+        bench::run();
+        return;
+    }
+
     // Init GLFW
     let mut glfw = window::init_glfw();
 
@@ -32,22 +75,93 @@ pub fn main() {
         title: String::from(SCR_TITLE),
     });
 
-    // Create a simulation (200x200 collision cells)
-    let mut sim = create_simulation(200);
+    // Create a simulation
+    let mut sim = create_simulation(SIM_SEED);
+    sim.set_parallel(true);
+
+    // Load the material registry (keeping the built-in one if that fails)
+    match load_materials(MATERIALS_PATH) {
+        Ok(materials) => if let Err(error) = sim.set_materials(materials) {
+            println!("ERROR::MATERIALS::REGISTRY_REJECTED\n{}", error);
+        },
+        Err(error) => println!("ERROR::MATERIALS::LOADING_FAILED\n{}", error),
+    }
+    let neutron = find_material(&sim, "neutron");
+    let reflector = find_material(&sim, "reflector");
+    let moderator = find_material(&sim, "moderator");
+    let absorber = find_material(&sim, "absorber");
+
+    // Record the chain-reaction tree for L to export? (off by default: it grows with every neutron)
+    if std::env::args().any(|arg| arg == "--lineage") {
+        sim.set_lineage_recording(true);
+    }
+
+    // Material spawned by right click (Tab cycles through the registry)
+    let mut selected = find_material(&sim, "fissile");
+
+    // Floor at the bottom, open everywhere else
+    let mut boundaries = create_boundaries((0.0, 0.0), (WORLD_WIDTH, WORLD_HEIGHT), Boundary::Open);
+    boundaries.bottom = Boundary::Wall;
+    sim.set_boundaries(boundaries);
+
+    // Containment vessel in the middle of the world
+    sim.add_collider(Collider::Polygon { points: vec![
+        (0.25 * WORLD_WIDTH, 0.1 * WORLD_HEIGHT),
+        (0.75 * WORLD_WIDTH, 0.1 * WORLD_HEIGHT),
+        (0.75 * WORLD_WIDTH, 0.9 * WORLD_HEIGHT),
+        (0.25 * WORLD_WIDTH, 0.9 * WORLD_HEIGHT),
+    ] });
+
+    // Control rods, hanging into the vessel from the top (Up/Down arrows move them)
+    let rod_radius = sim.material(absorber).radius;
+    let rods = vec![
+        sim.add_control_rod(absorber, (0.4 * WORLD_WIDTH, 0.5 * WORLD_HEIGHT), (0.4 * WORLD_WIDTH, 0.85 * WORLD_HEIGHT), rod_radius),
+        sim.add_control_rod(absorber, (0.6 * WORLD_WIDTH, 0.5 * WORLD_HEIGHT), (0.6 * WORLD_WIDTH, 0.85 * WORLD_HEIGHT), rod_radius),
+    ];
+
+    // Rods travel from fully inserted (tips just above the vessel floor) to fully withdrawn (tips at the vessel top)
+    for &rod in &rods {
+        sim.set_control_rod_travel(rod, (0.0, -0.35 * WORLD_HEIGHT), (0.0, 0.4 * WORLD_HEIGHT));
+    }
+
+    // Create the coolant (pumped up through the vessel from the bottom; the sim couples it to the particles every fixed step)
+    // (on the CPU with --cpu-fluid, or when there are no compute shaders)
+    let coolant: Box<dyn FluidSolver> = if std::env::args().any(|arg| arg == "--cpu-fluid") || !supports_compute_shaders() {
+        let grid_size = ((SCR_WIDTH / CPU_FLUID_CELL_SIZE) as usize, (SCR_HEIGHT / CPU_FLUID_CELL_SIZE) as usize);
+        Box::new(create_cpu_fluid((WORLD_WIDTH, WORLD_HEIGHT), grid_size))
+    } else {
+        Box::new(fluid::create_simulation((WORLD_WIDTH, WORLD_HEIGHT)))
+    };
+    sim.set_coolant(Some(coolant));
+    sim.add_pump(Pump {
+        position: (0.5 * WORLD_WIDTH, 0.15 * WORLD_HEIGHT),
+        radius: 0.1 * WORLD_WIDTH,
+        force: (0.0, COOLANT_PUMP_FORCE),
+    });
+
+    // Create a coolant renderer (glows where the coolant is hot)
+    let coolant_renderer = make_grid_renderer(include_str!("../shaders/coolant.frag"));
+    let coolant_texture = textures::create_grid_texture();     // For coolants on the CPU
 
     // Create a circle renderer
     let circle_renderer = create_circle_renderer();
 
+    // Create a line renderer (for static colliders)
+    let line_renderer = create_line_renderer();
+
+    // Create a frame clock
+    let mut clock = clock::create_clock();
+
     // Store last spawn time
     let mut last_spawn_time = -1000.0 as f64;
-    let mut last_cull_time = unsafe {glfwGetTime() as f64};
 
     // Render loop
     while !window.should_close() {
-        let t = unsafe {glfwGetTime() as f64};
+        let dt = clock.tick();
+        let t = clock.now();
 
         // Process events
-        process_events(&mut window, &events);
+        process_events(&mut window, &events, &sim, &mut selected);
 
         // Clear background
         unsafe { 
@@ -58,15 +172,21 @@ pub fn main() {
         // Is Space down?
         let space_down = window.get_key(glfw::Key::Space) == glfw::Action::Press;
 
-        // Simulate
-        // Should we cull?
-        if t - last_cull_time > 1.0 {
-            last_cull_time = t;
-            sim.simulate(true, space_down);
+        // Drive control rods (Down inserts, Up withdraws)
+        let rod_velocity = if window.get_key(glfw::Key::Down) == glfw::Action::Press {
+            (0.0, -ROD_SPEED)
+        } else if window.get_key(glfw::Key::Up) == glfw::Action::Press {
+            (0.0, ROD_SPEED)
         } else {
-            sim.simulate(false, space_down);
+            (0.0, 0.0)
+        };
+        for &rod in &rods {
+            sim.set_control_rod_velocity(rod, rod_velocity);
         }
 
+        // Simulate (the coolant too)
+        sim.step(dt, space_down);
+
         // Is CTRL down?
         let ctrl_down = window.get_key(glfw::Key::LeftControl) == glfw::Action::Press;
         
@@ -76,9 +196,8 @@ pub fn main() {
             let pos = window.get_cursor_pos();
 
             // Generate random momentum
-            let mx = (rand::random::<f32>() - 0.5) * N_MOMENTUM;
-            let my = (rand::random::<f32>() - 0.5) * N_MOMENTUM;
-            sim.add_particle_with_momentum((pos.0 as f32, SCR_HEIGHT as f32 - pos.1 as f32), N_RADIUS, (mx, my));
+            let momentum = sim.random_momentum(N_MOMENTUM);
+            spawn(&mut sim, neutron, screen_to_world(pos), momentum);
 
             // Set last spawn time
             last_spawn_time = t;
@@ -89,48 +208,88 @@ pub fn main() {
             // Get cursor position
             let pos = window.get_cursor_pos();
 
-            // Add fuel
-            sim.add_reflector((pos.0 as f32, SCR_HEIGHT as f32 - pos.1 as f32), 16.0);
+            // Add reflector
+            spawn(&mut sim, reflector, screen_to_world(pos), (0.0, 0.0));
 
             // Set last spawn time
             last_spawn_time = t;
         }
         
-        // Spawn neutron
+        // Spawn selected material (fuel by default)
         if t - last_spawn_time > 0.01 && window.get_mouse_button(glfw::MouseButtonRight) == glfw::Action::Press && !ctrl_down {
             // Get cursor position
             let pos = window.get_cursor_pos();
 
-            // Add fuel
-            sim.add_fissile((pos.0 as f32, SCR_HEIGHT as f32 - pos.1 as f32), FISSILE_RADIUS);
+            // Add particle
+            spawn(&mut sim, selected, screen_to_world(pos), (0.0, 0.0));
 
             // Set last spawn time
             last_spawn_time = t;
         }
         
+        // Spawn moderator
+        if t - last_spawn_time > 0.01 && window.get_mouse_button(glfw::MouseButtonMiddle) == glfw::Action::Press {
+            // Get cursor position
+            let pos = window.get_cursor_pos();
+
+            // Add moderator
+            spawn(&mut sim, moderator, screen_to_world(pos), (0.0, 0.0));
+
+            // Set last spawn time
+            last_spawn_time = t;
+        }
+
         // Spawn starter cap
         // if t - last_spawn_time > 0.01 && window.get_mouse_button(glfw::MouseButtonRight) == glfw::Action::Press && ctrl_down {
         //     // Get cursor position
         //     let pos = window.get_cursor_pos();
 
         //     // Add fuel
-        //     sim.add_starter_cap((pos.0 as f32, SCR_HEIGHT as f32 - pos.1 as f32), 4.0);
+        //     spawn(&mut sim, find_material(&sim, "starter_cap"), screen_to_world(pos), (0.0, 0.0));
 
         //     // Set last spawn time
         //     last_spawn_time = t;
         // }
         
+        // Draw coolant (under everything else)
+        if let Some(coolant) = sim.coolant() {
+            match coolant.heat_map() {
+                HeatMap::Texture(texture) => coolant_renderer.render_grid(texture),
+                HeatMap::Grid { width, height, values } => {
+                    textures::upload_grid_texture(coolant_texture, width, height, values);
+                    coolant_renderer.render_grid(coolant_texture);
+                },
+            }
+        }
+
         // Draw simulation
-        for particle in &sim.particles {
-            if particle.particle_type == ParticleType::Neutron {
-                circle_renderer.draw(particle.position, particle.mass, (1.0, 0.0, 0.0, 1.0))
-            } else if particle.particle_type == ParticleType::Fissile {
-                circle_renderer.draw(particle.position, particle.mass, (0.0, 1.0, 0.0, 1.0))
-            } else if particle.particle_type == ParticleType::StarterCap {
-                circle_renderer.draw(particle.position, particle.mass, (1.0, 1.0, 0.0, 1.0))
+        for i in 0..sim.particles.len() {
+            let position = world_to_screen(sim.particles.position(i));
+            let radius = sim.particles.radius(i) * SCR_WIDTH as f32 / WORLD_WIDTH;
+            let material = sim.material(sim.particles.particle_type(i));
+            let color = material.color;
+
+            // Shade neutrons by energy group (slower = dimmer)
+            let shade = if material.neutron {
+                match sim.energy_group(i) {
+                    EnergyGroup::Fast => 1.0,
+                    EnergyGroup::Epithermal => 0.6,
+                    EnergyGroup::Thermal => 0.3,
+                }
             } else {
-                circle_renderer.draw(particle.position, particle.mass, (1.0, 1.0, 1.0, 1.0))
-            }
+                1.0
+            };
+
+            // Hot particles glow
+            let glow = ((sim.particles.temperature(i) - AMBIENT_TEMPERATURE) / GLOW_TEMPERATURE).max(0.0).min(1.0);
+            let mix = |base: f32, hot: f32| base * shade * (1.0 - glow) + hot * glow;
+            circle_renderer.draw(position, radius, (mix(color.0, GLOW_COLOR.0), mix(color.1, GLOW_COLOR.1), mix(color.2, GLOW_COLOR.2), color.3))
+        }
+
+        // Draw static colliders
+        for collider in sim.colliders() {
+            let coords: Vec<(f32, f32)> = outline(collider, 32).into_iter().map(world_to_screen).collect();
+            line_renderer.draw_polyline(&coords, COLLIDER_LINE_WIDTH);
         }
 
         // Swap buffers (present what we just drew)
@@ -141,8 +300,41 @@ pub fn main() {
     }
 }
 
+// Function for looking up a material the demo needs
+fn find_material(sim: &Simulation, name: &str) -> ParticleType {
+    return sim.find_material(name).expect(&format!("Material registry has no \"{}\" material", name));
+}
+
+// Function for spawning a particle with its material's default radius and mass
+fn spawn(sim: &mut Simulation, particle_type: ParticleType, position: (f32, f32), momentum: (f32, f32)) {
+    let (radius, mass) = (sim.material(particle_type).radius, sim.material(particle_type).mass);
+    sim.add_particle_with_momentum(particle_type, position, radius, mass, momentum);
+}
+
+// Function for checking the GL context has compute shaders (4.3+)
+fn supports_compute_shaders() -> bool {
+    let (mut major, mut minor) = (0, 0);
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    return (major, minor) >= (4, 3);
+}
+
+// Function for mapping a cursor position (pixels, y down) to world units (y up)
+fn screen_to_world(pos: (f64, f64)) -> (f32, f32) {
+    let x = pos.0 as f32 * WORLD_WIDTH / SCR_WIDTH as f32;
+    let y = (SCR_HEIGHT as f32 - pos.1 as f32) * WORLD_HEIGHT / SCR_HEIGHT as f32;
+    return (x, y);
+}
+
+// Function for mapping a world position to screen pixels (y up)
+fn world_to_screen(position: (f32, f32)) -> (f32, f32) {
+    return (position.0 * SCR_WIDTH as f32 / WORLD_WIDTH, position.1 * SCR_HEIGHT as f32 / WORLD_HEIGHT);
+}
+
 // Function for handling events
-fn process_events(window: &mut glfw::Window, events: &GlfwReceiver<(f64, glfw::WindowEvent)>) {
+fn process_events(window: &mut glfw::Window, events: &GlfwReceiver<(f64, glfw::WindowEvent)>, sim: &Simulation, selected: &mut ParticleType) {
     // Loop through all flushed messages
     for (_, event) in glfw::flush_messages(events) {
         // Match events by type
@@ -154,6 +346,33 @@ fn process_events(window: &mut glfw::Window, events: &GlfwReceiver<(f64, glfw::W
 
             // Key events
             glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => window.set_should_close(true),
+            glfw::WindowEvent::Key(Key::Tab, _, Action::Press, _) => {
+                *selected = ParticleType((selected.0 + 1) % sim.materials().len());
+                println!("Spawning {}", sim.material(*selected).name);
+            }
+            glfw::WindowEvent::Key(Key::S, _, Action::Press, _) => {
+                let stats = sim.stats();
+                println!("t = {:.1} s: {} fissions, {} captures, {} decays, {:.3e} released, power {:.3e}/s",
+                    stats.time, stats.fissions.count, stats.captures.count, stats.decays.count, stats.energy_released, stats.power);
+                let neutrons = stats.history.last().map_or(0, |sample| sample.neutrons);
+                match stats.k_effective {
+                    Some(k) => println!("{} neutrons, generations {}-{}: k-effective {:.3}", neutrons, stats.k_generations.0, stats.k_generations.1, k),
+                    None => println!("{} neutrons, k-effective unknown (no generation done yet)", neutrons),
+                }
+            }
+            glfw::WindowEvent::Key(Key::L, _, Action::Press, _) => {
+                let lineage = sim.lineage();
+                if !lineage.recording {
+                    println!("ERROR::LINEAGE::NOT_RECORDING\nRun with --lineage to record the chain-reaction tree");
+                } else {
+                    for (path, contents) in vec![(LINEAGE_DOT_PATH, to_dot(lineage)), (LINEAGE_JSON_PATH, to_json(lineage))] {
+                        match fs::write(path, contents) {
+                            Ok(_) => println!("Wrote {} neutrons to {}", lineage.nodes.len(), path),
+                            Err(error) => println!("ERROR::LINEAGE::WRITE_FAILED\n{}: {}", path, error),
+                        }
+                    }
+                }
+            }
 
             // Fallthrough case
             _ => {}
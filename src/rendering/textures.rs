@@ -65,4 +65,38 @@ pub fn copy_grid_texture(tex_src_id: GLuint, tex_dest_id: GLuint) {
     unsafe {
         gl::CopyImageSubData(tex_src_id, gl::TEXTURE_2D, 0, 0, 0, 0, tex_dest_id, gl::TEXTURE_2D, 0, 0, 0, 0, width, height, 1);
     }
-}
\ No newline at end of file
+}
+
+// Function to (re)fill a grid texture from a single-channel grid (row-major, bottom row first; resizes it)
+pub fn upload_grid_texture(tex_id: GLuint, width: usize, height: usize, values: &[f32]) {
+    unsafe {
+        gl::BindTexture(gl::TEXTURE_2D, tex_id);
+        gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA32F as i32, width as i32, height as i32, 0, gl::RED, gl::FLOAT, values.as_ptr() as *const c_void);
+    }
+}
+
+// Function to create a new accumulator texture (one int channel, zeroed; for image atomics)
+pub fn create_accumulator_texture() -> GLuint {
+    // Use screen resolution
+    let width = crate::SCR_WIDTH as i32;
+    let height = crate::SCR_HEIGHT as i32;
+
+    // Create texture
+    let tex_id: GLuint = unsafe {
+        let mut id: GLuint = 0;
+        gl::GenTextures(1, &mut id);
+        id
+    };
+
+    // Set texture size/format (integer textures can't be filtered)
+    unsafe {
+        let data: Vec<i32> = vec![0; width as usize * height as usize];
+        gl::BindTexture(gl::TEXTURE_2D, tex_id);
+        gl::TexImage2D(gl::TEXTURE_2D, 0, gl::R32I as i32, width, height, 0, gl::RED_INTEGER, gl::INT, data.as_ptr() as *const c_void);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+    }
+
+    // Return texture ID
+    return tex_id;
+}
@@ -0,0 +1,236 @@
+// Boundary condition on one side of the world
+#[derive(Clone, Copy, PartialEq)]
+pub enum Boundary {
+    Wall,       // Reflects particles (bounciness from the particle's material)
+    Periodic,   // Particles leaving through this side come back in on the opposite one
+    Open,       // Absorbs particles (despawned once their center leaves the world)
+}
+
+// World extent + boundary condition per side
+// (in simulation units, independent of the window size)
+// An axis with a periodic side is periodic for contacts too: its two edges touch
+#[derive(Clone, Copy)]
+pub struct Boundaries {
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+    pub left: Boundary,
+    pub right: Boundary,
+    pub bottom: Boundary,
+    pub top: Boundary,
+}
+
+// Create boundaries with the same condition on every side
+pub fn create_boundaries(min: (f32, f32), max: (f32, f32), boundary: Boundary) -> Boundaries {
+    return Boundaries {
+        min: min,
+        max: max,
+        left: boundary,
+        right: boundary,
+        bottom: boundary,
+        top: boundary,
+    }
+}
+
+// Apply the boundaries to one particle's Verlet state
+// (checked_position wraps along with the particle, see ParticleStorage::checked_position)
+// Returns false if the particle escaped through an open side
+pub fn apply_boundaries(boundaries: &Boundaries, position: &mut (f32, f32), last_position: &mut (f32, f32), checked_position: &mut (f32, f32), radius: f32, restitution: f32) -> bool {
+    let inside_x = apply_axis(boundaries.min.0, boundaries.max.0, boundaries.left, boundaries.right, &mut position.0, &mut last_position.0, &mut checked_position.0, radius, restitution);
+    let inside_y = apply_axis(boundaries.min.1, boundaries.max.1, boundaries.bottom, boundaries.top, &mut position.1, &mut last_position.1, &mut checked_position.1, radius, restitution);
+    return inside_x && inside_y;
+}
+
+// Which axes (x, y) are periodic (particles leaving through either side wrap around)
+pub fn periodic_axes(boundaries: &Boundaries) -> (bool, bool) {
+    return (
+        boundaries.left == Boundary::Periodic || boundaries.right == Boundary::Periodic,
+        boundaries.bottom == Boundary::Periodic || boundaries.top == Boundary::Periodic,
+    );
+}
+
+// Separation a - b, the short way across any periodic seam (minimum image)
+pub fn separation(boundaries: &Boundaries, a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    let (periodic_x, periodic_y) = periodic_axes(boundaries);
+    let mut d = (a.0 - b.0, a.1 - b.1);
+    if periodic_x {
+        d.0 = nearest_image(d.0, boundaries.max.0 - boundaries.min.0);
+    }
+    if periodic_y {
+        d.1 = nearest_image(d.1, boundaries.max.1 - boundaries.min.1);
+    }
+    return d;
+}
+
+// Copies of the particles within reach of a periodic seam, shifted to the far side of it
+// (so a broadphase that knows nothing of seams pairs them with their neighbors over there)
+// Returns (image position, index of the particle it copies)
+pub fn periodic_images(boundaries: &Boundaries, positions: &[(f32, f32)], reach: f32) -> Vec<((f32, f32), usize)> {
+    let mut images = vec![];
+    let (periodic_x, periodic_y) = periodic_axes(boundaries);
+    if !periodic_x && !periodic_y {
+        return images;
+    }
+
+    let size = (boundaries.max.0 - boundaries.min.0, boundaries.max.1 - boundaries.min.1);
+    for (i, position) in positions.iter().enumerate() {
+        // Shift per axis (0 = not near a seam): near the low edge -> +size, near the high edge -> -size
+        let shift_x = if periodic_x { seam_shift(position.0, boundaries.min.0, boundaries.max.0, reach) } else { 0.0 };
+        let shift_y = if periodic_y { seam_shift(position.1, boundaries.min.1, boundaries.max.1, reach) } else { 0.0 };
+        if shift_x != 0.0 {
+            images.push(((position.0 + shift_x * size.0, position.1), i));
+        }
+        if shift_y != 0.0 {
+            images.push(((position.0, position.1 + shift_y * size.1), i));
+        }
+        if shift_x != 0.0 && shift_y != 0.0 {
+            images.push(((position.0 + shift_x * size.0, position.1 + shift_y * size.1), i));
+        }
+    }
+    return images;
+}
+
+// Helper function to pick which way to copy a coordinate across a periodic axis (+1, -1 or 0 = not near a seam)
+fn seam_shift(x: f32, min: f32, max: f32, reach: f32) -> f32 {
+    if x - min < reach {
+        return 1.0;
+    }
+    if max - x < reach {
+        return -1.0;
+    }
+    return 0.0;
+}
+
+// Helper function to wrap a separation along a periodic axis of the given length into [-length/2, length/2]
+fn nearest_image(d: f32, length: f32) -> f32 {
+    return d - length * (d / length).round();
+}
+
+// Helper function to apply the low/high sides of one axis
+fn apply_axis(min: f32, max: f32, low: Boundary, high: Boundary, x: &mut f32, last: &mut f32, checked: &mut f32, radius: f32, restitution: f32) -> bool {
+    // Displacement per substep (velocity * dt)
+    let v = *x - *last;
+
+    // Low side
+    if *x - radius < min {
+        match low {
+            Boundary::Wall => {
+                // Project onto the wall, then bounce (only if still heading into it)
+                *x = min + radius;
+                *last = *x - if v < 0.0 { -restitution * v } else { v };
+            }
+            Boundary::Periodic => {
+                if *x < min {
+                    *x += max - min;
+                    *last += max - min;
+                    *checked += max - min;
+                }
+            }
+            Boundary::Open => {
+                if *x < min {
+                    return false;
+                }
+            }
+        }
+    }
+
+    // High side
+    if *x + radius > max {
+        match high {
+            Boundary::Wall => {
+                *x = max - radius;
+                *last = *x - if v > 0.0 { -restitution * v } else { v };
+            }
+            Boundary::Periodic => {
+                if *x >= max {
+                    *x -= max - min;
+                    *last -= max - min;
+                    *checked -= max - min;
+                }
+            }
+            Boundary::Open => {
+                if *x >= max {
+                    return false;
+                }
+            }
+        }
+    }
+
+    return true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper function to apply boundaries to a particle at position moving by v per substep
+    // Returns (inside, position, displacement per substep, checked position)
+    fn apply(boundaries: &Boundaries, position: (f32, f32), v: (f32, f32)) -> (bool, (f32, f32), (f32, f32), (f32, f32)) {
+        let mut position = position;
+        let mut last = (position.0 - v.0, position.1 - v.1);
+        let mut checked = position;
+        let inside = apply_boundaries(boundaries, &mut position, &mut last, &mut checked, 4.0, 0.5);
+        return (inside, position, (position.0 - last.0, position.1 - last.1), checked);
+    }
+
+    #[test]
+    fn each_side_applies_its_own_boundary() {
+        // Walled on the left, periodic bottom/top, open on the right
+        let mut boundaries = create_boundaries((0.0, 0.0), (100.0, 50.0), Boundary::Periodic);
+        boundaries.left = Boundary::Wall;
+        boundaries.right = Boundary::Open;
+
+        // Wall: pushed back in, bounced with the restitution
+        let (inside, position, v, _) = apply(&boundaries, (2.0, 20.0), (-6.0, 1.0));
+        assert!(inside);
+        assert_eq!(position, (4.0, 20.0));
+        assert_eq!(v, (3.0, 1.0));
+
+        // Open: kept while the center is inside, gone once it's out
+        assert!(apply(&boundaries, (98.0, 20.0), (6.0, 0.0)).0);
+        assert!(!apply(&boundaries, (100.5, 20.0), (6.0, 0.0)).0);
+
+        // Periodic: wrapped to the other side with the same velocity (checked position too)
+        let (inside, position, v, checked) = apply(&boundaries, (50.0, 51.0), (0.0, 3.0));
+        assert!(inside);
+        assert_eq!(position, (50.0, 1.0));
+        assert_eq!(v, (0.0, 3.0));
+        assert_eq!(checked, (50.0, 1.0));
+        let (_, position, _, _) = apply(&boundaries, (50.0, -2.0), (0.0, -3.0));
+        assert_eq!(position, (50.0, 48.0));
+    }
+
+    #[test]
+    fn walls_only_bounce_particles_heading_into_them() {
+        // Overlapping the top wall but already moving away: projected, not reversed
+        let boundaries = create_boundaries((0.0, 0.0), (100.0, 50.0), Boundary::Wall);
+        let (inside, position, v, _) = apply(&boundaries, (50.0, 48.0), (0.0, -2.0));
+        assert!(inside);
+        assert_eq!(position, (50.0, 46.0));
+        assert_eq!(v, (0.0, -2.0));
+    }
+
+    #[test]
+    fn periodic_separation_takes_the_short_way() {
+        let mut boundaries = create_boundaries((0.0, 0.0), (100.0, 50.0), Boundary::Wall);
+        assert_eq!(separation(&boundaries, (98.0, 2.0), (1.0, 48.0)), (97.0, -46.0));
+
+        // Only the periodic axis wraps, and one periodic side is enough
+        boundaries.right = Boundary::Periodic;
+        assert_eq!(separation(&boundaries, (98.0, 2.0), (1.0, 48.0)), (-3.0, -46.0));
+        assert_eq!(periodic_axes(&boundaries), (true, false));
+    }
+
+    #[test]
+    fn images_copy_particles_across_nearby_seams() {
+        let boundaries = create_boundaries((0.0, 0.0), (100.0, 50.0), Boundary::Periodic);
+        let positions = vec![(50.0, 25.0), (2.0, 25.0), (97.0, 48.0)];
+        let images = periodic_images(&boundaries, &positions, 5.0);
+
+        // Nothing for the middle one, one for the edge one, three for the corner one
+        assert_eq!(images, vec![
+            ((102.0, 25.0), 1),
+            ((-3.0, 48.0), 2), ((97.0, -2.0), 2), ((-3.0, -2.0), 2),
+        ]);
+        assert!(periodic_images(&create_boundaries((0.0, 0.0), (100.0, 50.0), Boundary::Wall), &positions, 5.0).is_empty());
+    }
+}
@@ -0,0 +1,404 @@
+use rayon::prelude::*;
+
+// Particles per parallel chunk of the spatial hash's pair search
+const PAIR_CHUNK: usize = 4096;
+
+// Broadphase: finds candidate pairs of particles whose bounding boxes overlap
+// (the narrowphase in particles.rs does the exact circle test)
+pub trait Broadphase {
+    // Push every candidate pair (i, j) with i < j into pairs
+    fn find_pairs(&mut self, positions: &[(f32, f32)], radii: &[f32], pairs: &mut Vec<(usize, usize)>);
+
+    // Search on the rayon pool? (must push the same pairs in the same order as serial)
+    fn set_parallel(&mut self, _parallel: bool) {}
+}
+
+// Available broadphase backends
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BroadphaseKind {
+    SpatialHash,
+    SweepAndPrune,
+    Quadtree,
+}
+
+// Create a broadphase backend of the given kind
+pub fn create_broadphase(kind: BroadphaseKind) -> Box<dyn Broadphase> {
+    return match kind {
+        BroadphaseKind::SpatialHash => Box::new(create_spatial_hash()),
+        BroadphaseKind::SweepAndPrune => Box::new(create_sweep_and_prune()),
+        BroadphaseKind::Quadtree => Box::new(create_quadtree()),
+    }
+}
+
+// Uniform spatial hash
+// Cells are square with side = largest diameter, so any overlapping pair
+// is in the same or neighboring cells. Cells hash into a fixed bucket table
+// (counting sort), so memory is O(n) no matter how far particles spread.
+pub struct SpatialHash {
+    cells: Vec<(i32, i32)>,     // Cell of each particle
+    bucket_start: Vec<usize>,   // Start of each bucket in entries (+1 sentinel)
+    entries: Vec<usize>,        // Particle indices, grouped by bucket
+    parallel: bool,             // Search for pairs in parallel chunks
+}
+
+// Create an empty spatial hash (buffers are reused between builds)
+pub fn create_spatial_hash() -> SpatialHash {
+    return SpatialHash {
+        cells: vec![],
+        bucket_start: vec![],
+        entries: vec![],
+        parallel: false,
+    }
+}
+
+// Hash a cell coordinate into a bucket
+fn hash_cell(cell: (i32, i32), buckets: usize) -> usize {
+    let h = (cell.0 as i64).wrapping_mul(92837111) ^ (cell.1 as i64).wrapping_mul(689287499);
+    return (h as u64 % buckets as u64) as usize;
+}
+
+impl Broadphase for SpatialHash {
+    fn find_pairs(&mut self, positions: &[(f32, f32)], radii: &[f32], pairs: &mut Vec<(usize, usize)>) {
+        let n = positions.len();
+        if n < 2 {
+            return;
+        }
+
+        // Step 1: Cell size from the largest radius
+        let max_radius = radii.iter().cloned().fold(0.0, f32::max);
+        if max_radius <= 0.0 {
+            return;
+        }
+        let cell_size = 2.0 * max_radius;
+
+        // Step 2: Assign particles to cells
+        let cell_of = |pos: &(f32, f32)| ((pos.0 / cell_size).floor() as i32, (pos.1 / cell_size).floor() as i32);
+        self.cells.clear();
+        if self.parallel {
+            self.cells.par_extend(positions.par_iter().map(cell_of));
+        } else {
+            self.cells.extend(positions.iter().map(cell_of));
+        }
+
+        // Step 3: Counting sort into buckets (2 buckets per particle keeps collisions rare)
+        let buckets = 2 * n;
+        self.bucket_start.clear();
+        self.bucket_start.resize(buckets + 1, 0);
+        for cell in &self.cells {
+            self.bucket_start[hash_cell(*cell, buckets) + 1] += 1;
+        }
+        for b in 0..buckets {
+            self.bucket_start[b + 1] += self.bucket_start[b];
+        }
+        self.entries.clear();
+        self.entries.resize(n, 0);
+        let mut fill = self.bucket_start.clone();
+        for i in 0..n {
+            let b = hash_cell(self.cells[i], buckets);
+            self.entries[fill[b]] = i;
+            fill[b] += 1;
+        }
+
+        // Step 4: Test each particle against its own cell + the 8 neighboring cells
+        // (in parallel: chunks of particles, concatenated in order, so the pairs match serial)
+        if self.parallel {
+            let hash = &*self;
+            let chunks: Vec<Vec<(usize, usize)>> = (0..(n + PAIR_CHUNK - 1) / PAIR_CHUNK).into_par_iter()
+                .map(|chunk| {
+                    let mut local = vec![];
+                    for i in chunk * PAIR_CHUNK..(n.min((chunk + 1) * PAIR_CHUNK)) {
+                        hash_pairs_of(hash, i, buckets, positions, radii, &mut local);
+                    }
+                    local
+                })
+                .collect();
+            for chunk in chunks {
+                pairs.extend(chunk);
+            }
+        } else {
+            for i in 0..n {
+                hash_pairs_of(self, i, buckets, positions, radii, pairs);
+            }
+        }
+    }
+
+    fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+}
+
+// Helper function to push particle i's pairs (with later particles) from its own + the 8 neighboring cells
+fn hash_pairs_of(hash: &SpatialHash, i: usize, buckets: usize, positions: &[(f32, f32)], radii: &[f32], pairs: &mut Vec<(usize, usize)>) {
+    let (cx, cy) = hash.cells[i];
+    for ox in -1..2 {
+        for oy in -1..2 {
+            let cell = (cx + ox, cy + oy);
+            let b = hash_cell(cell, buckets);
+            for e in hash.bucket_start[b]..hash.bucket_start[b + 1] {
+                let j = hash.entries[e];
+
+                // Each pair once (and skip other cells sharing this bucket)
+                if j <= i || hash.cells[j] != cell {
+                    continue;
+                }
+
+                // Bounding boxes overlap?
+                let reach = radii[i] + radii[j];
+                if (positions[i].0 - positions[j].0).abs() < reach && (positions[i].1 - positions[j].1).abs() < reach {
+                    pairs.push((i, j));
+                }
+            }
+        }
+    }
+}
+
+// Sort-based sweep and prune (along x)
+// Good when sizes are mixed (no cell size to tune), and the order from the
+// last build is reused so re-sorting a coherent scene is close to O(n)
+pub struct SweepAndPrune {
+    order: Vec<usize>,      // Particle indices, sorted by left edge
+    active: Vec<usize>,     // Particles whose x-interval overlaps the sweep line
+}
+
+// Create an empty sweep and prune
+pub fn create_sweep_and_prune() -> SweepAndPrune {
+    return SweepAndPrune {
+        order: vec![],
+        active: vec![],
+    }
+}
+
+impl Broadphase for SweepAndPrune {
+    fn find_pairs(&mut self, positions: &[(f32, f32)], radii: &[f32], pairs: &mut Vec<(usize, usize)>) {
+        let n = positions.len();
+
+        // Step 1: Particle count changed? Start over from identity order
+        if self.order.len() != n {
+            self.order = (0..n).collect();
+        }
+
+        // Step 2: Sort by left edge (stable sort is adaptive, so nearly-sorted is cheap)
+        // (total order, so a NaN position sorts to the end instead of breaking the sort)
+        self.order.sort_by(|&a, &b| {
+            let left_a = positions[a].0 - radii[a];
+            let left_b = positions[b].0 - radii[b];
+            left_a.total_cmp(&left_b)
+        });
+
+        // Step 3: Sweep
+        self.active.clear();
+        for &i in &self.order {
+            let left = positions[i].0 - radii[i];
+
+            // Drop particles that end before this one starts
+            self.active.retain(|&j| positions[j].0 + radii[j] > left);
+
+            // Everything still active overlaps in x, so check y
+            for &j in &self.active {
+                if (positions[i].1 - positions[j].1).abs() < radii[i] + radii[j] {
+                    pairs.push((i.min(j), i.max(j)));
+                }
+            }
+
+            self.active.push(i);
+        }
+    }
+}
+
+// Quadtree settings
+const QUADTREE_CAPACITY: usize = 8;
+const QUADTREE_MAX_DEPTH: usize = 12;
+
+// Quadtree node
+// (a particle lives in the deepest node that fully contains its bounding box,
+// so big fissile particles sit higher up than the small neutrons around them)
+struct QuadNode {
+    min: (f32, f32),
+    max: (f32, f32),
+    depth: usize,
+    first_child: usize,     // Index of the first of 4 children (0 = leaf)
+    items: Vec<usize>,
+}
+
+// Region quadtree, rebuilt every call
+pub struct Quadtree {
+    nodes: Vec<QuadNode>,
+    stack: Vec<(usize, usize)>,     // DFS stack of (node, ancestor item count)
+    ancestors: Vec<usize>,          // Items of the nodes on the current DFS path
+}
+
+// Create an empty quadtree
+pub fn create_quadtree() -> Quadtree {
+    return Quadtree {
+        nodes: vec![],
+        stack: vec![],
+        ancestors: vec![],
+    }
+}
+
+// Helper function to check if a node fully contains a particle's bounding box
+fn node_contains(node: &QuadNode, position: (f32, f32), radius: f32) -> bool {
+    return position.0 - radius >= node.min.0 && position.0 + radius <= node.max.0
+        && position.1 - radius >= node.min.1 && position.1 + radius <= node.max.1;
+}
+
+// Helper function to insert a particle into the subtree at node
+fn quadtree_insert(tree: &mut Quadtree, node: usize, i: usize, positions: &[(f32, f32)], radii: &[f32]) {
+    // Step 1: Descend into a child if one fully contains the particle
+    let first_child = tree.nodes[node].first_child;
+    if first_child != 0 {
+        for child in first_child..first_child + 4 {
+            if node_contains(&tree.nodes[child], positions[i], radii[i]) {
+                quadtree_insert(tree, child, i, positions, radii);
+                return;
+            }
+        }
+        tree.nodes[node].items.push(i);
+        return;
+    }
+
+    // Step 2: Leaf, so store here
+    tree.nodes[node].items.push(i);
+
+    // Step 3: Split if over capacity, then push down whatever fits into the children
+    if tree.nodes[node].items.len() > QUADTREE_CAPACITY && tree.nodes[node].depth < QUADTREE_MAX_DEPTH {
+        let (min, max, depth) = (tree.nodes[node].min, tree.nodes[node].max, tree.nodes[node].depth);
+        let mid = (0.5 * (min.0 + max.0), 0.5 * (min.1 + max.1));
+        let first_child = tree.nodes.len();
+        for &(cmin, cmax) in &[(min, mid), ((mid.0, min.1), (max.0, mid.1)), ((min.0, mid.1), (mid.0, max.1)), (mid, max)] {
+            tree.nodes.push(QuadNode { min: cmin, max: cmax, depth: depth + 1, first_child: 0, items: vec![] });
+        }
+        tree.nodes[node].first_child = first_child;
+
+        let items = ::std::mem::replace(&mut tree.nodes[node].items, vec![]);
+        for item in items {
+            quadtree_insert(tree, node, item, positions, radii);
+        }
+    }
+}
+
+impl Broadphase for Quadtree {
+    fn find_pairs(&mut self, positions: &[(f32, f32)], radii: &[f32], pairs: &mut Vec<(usize, usize)>) {
+        let n = positions.len();
+        if n < 2 {
+            return;
+        }
+
+        // Step 1: Root = square bounding all particles
+        let mut min = (::std::f32::MAX, ::std::f32::MAX);
+        let mut max = (::std::f32::MIN, ::std::f32::MIN);
+        for i in 0..n {
+            min = (min.0.min(positions[i].0 - radii[i]), min.1.min(positions[i].1 - radii[i]));
+            max = (max.0.max(positions[i].0 + radii[i]), max.1.max(positions[i].1 + radii[i]));
+        }
+        let size = (max.0 - min.0).max(max.1 - min.1);
+        self.nodes.clear();
+        self.nodes.push(QuadNode { min: min, max: (min.0 + size, min.1 + size), depth: 0, first_child: 0, items: vec![] });
+
+        // Step 2: Insert particles
+        for i in 0..n {
+            quadtree_insert(self, 0, i, positions, radii);
+        }
+
+        // Step 3: DFS; each item can only overlap items in its own node, its ancestors or its descendants
+        self.stack.clear();
+        self.ancestors.clear();
+        self.stack.push((0, 0));
+        while let Some((node, ancestor_count)) = self.stack.pop() {
+            self.ancestors.truncate(ancestor_count);
+
+            let items = &self.nodes[node].items;
+            for a in 0..items.len() {
+                let i = items[a];
+
+                // Against later items in the same node
+                for &j in &items[a + 1..] {
+                    let reach = radii[i] + radii[j];
+                    if (positions[i].0 - positions[j].0).abs() < reach && (positions[i].1 - positions[j].1).abs() < reach {
+                        pairs.push((i.min(j), i.max(j)));
+                    }
+                }
+
+                // Against items of ancestors
+                for &j in &self.ancestors {
+                    let reach = radii[i] + radii[j];
+                    if (positions[i].0 - positions[j].0).abs() < reach && (positions[i].1 - positions[j].1).abs() < reach {
+                        pairs.push((i.min(j), i.max(j)));
+                    }
+                }
+            }
+
+            // Descend
+            let first_child = self.nodes[node].first_child;
+            if first_child != 0 {
+                self.ancestors.extend_from_slice(&self.nodes[node].items);
+                for child in first_child..first_child + 4 {
+                    self.stack.push((child, self.ancestors.len()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_spatial_hash_matches_serial() {
+        // Enough particles for several chunks, jittered so cells hold a varying number
+        let positions: Vec<(f32, f32)> = (0..3 * PAIR_CHUNK)
+            .map(|k| ((k % 128) as f32 * 7.0 + (k % 7) as f32, (k / 128) as f32 * 7.0 + (k % 5) as f32))
+            .collect();
+        let radii = vec![4.0; positions.len()];
+
+        let (mut serial, mut parallel) = (create_spatial_hash(), create_spatial_hash());
+        parallel.set_parallel(true);
+        let (mut serial_pairs, mut parallel_pairs) = (vec![], vec![]);
+        serial.find_pairs(&positions, &radii, &mut serial_pairs);
+        parallel.find_pairs(&positions, &radii, &mut parallel_pairs);
+
+        assert!(!serial_pairs.is_empty());
+        assert_eq!(serial_pairs, parallel_pairs);
+    }
+
+    #[test]
+    fn backends_find_the_same_pairs() {
+        // Scattered neutrons, fuel and big reflectors (mixed radii), some overlapping several others
+        let mut positions = vec![];
+        let mut radii = vec![];
+        for k in 0..2000 {
+            let h = (k as u32).wrapping_mul(2654435761);
+            positions.push(((h % 997) as f32 * 0.6, (h / 997 % 991) as f32 * 0.6));
+            radii.push(match k % 10 { 0..=6 => 1.5, 7 | 8 => 6.0, _ => 20.0 });
+        }
+
+        let mut found = vec![];
+        for kind in &[BroadphaseKind::SpatialHash, BroadphaseKind::SweepAndPrune, BroadphaseKind::Quadtree] {
+            let mut pairs = vec![];
+            create_broadphase(*kind).find_pairs(&positions, &radii, &mut pairs);
+            pairs.sort();
+            pairs.dedup();
+            found.push(pairs);
+        }
+
+        assert!(found[0].len() > 1000);
+        assert_eq!(found[0], found[1]);
+        assert_eq!(found[0], found[2]);
+    }
+
+    #[test]
+    fn sweep_and_prune_skips_nan_positions() {
+        let positions = vec![(0.0, 0.0), (::std::f32::NAN, 0.0), (3.0, 0.0), (1.0, ::std::f32::NAN), (5.0, 1.0)];
+        let radii = vec![2.0; positions.len()];
+
+        let mut pairs = vec![];
+        let mut sweep = create_sweep_and_prune();
+        for _ in 0..2 {
+            pairs.clear();
+            sweep.find_pairs(&positions, &radii, &mut pairs);
+        }
+        pairs.sort();
+        assert_eq!(pairs, vec![(0, 2), (2, 4)]);
+    }
+}
@@ -0,0 +1,149 @@
+use std::f32::consts::PI;
+
+// Static (immovable) geometry particles collide with
+pub enum Collider {
+    // Line segment (particles bounce off either side)
+    Segment { from: (f32, f32), to: (f32, f32) },
+
+    // Closed outline through points (hollow, so it can contain particles)
+    Polygon { points: Vec<(f32, f32)> },
+
+    // Solid disc
+    Circle { center: (f32, f32), radius: f32 },
+}
+
+// Resolve a particle against one collider (position/last_position are Verlet state)
+// Contacts push the particle out and reflect its velocity with the given restitution
+// (previous is where the particle was last known to be clear of every collider, for swept tests)
+// Returns true if there was a contact
+pub fn collide(collider: &Collider, previous: (f32, f32), position: &mut (f32, f32), last_position: &mut (f32, f32), radius: f32, restitution: f32) -> bool {
+    return match *collider {
+        Collider::Segment { from, to } => {
+            collide_segment(from, to, previous, position, last_position, radius, restitution)
+        }
+        Collider::Polygon { ref points } => {
+            let mut hit = false;
+            for k in 0..points.len() {
+                hit |= collide_segment(points[k], points[(k + 1) % points.len()], previous, position, last_position, radius, restitution);
+            }
+            hit
+        }
+        Collider::Circle { center, radius: collider_radius } => {
+            collide_circle(center, collider_radius, previous, position, last_position, radius, restitution)
+        }
+    }
+}
+
+// Outline of a collider for drawing (circles use the given number of segments)
+pub fn outline(collider: &Collider, circle_segments: usize) -> Vec<(f32, f32)> {
+    return match *collider {
+        Collider::Segment { from, to } => vec![from, to],
+        Collider::Polygon { ref points } => {
+            let mut coords = points.clone();
+            if let Some(&first) = points.first() {
+                coords.push(first);
+            }
+            coords
+        }
+        Collider::Circle { center, radius } => {
+            (0..circle_segments + 1)
+                .map(|k| {
+                    let angle = 2.0 * PI * k as f32 / circle_segments as f32;
+                    (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+                })
+                .collect()
+        }
+    }
+}
+
+// Helper function to resolve a particle against segment a-b
+// (swept, so a fast particle that crossed the line this substep is caught too)
+fn collide_segment(a: (f32, f32), b: (f32, f32), previous: (f32, f32), position: &mut (f32, f32), last_position: &mut (f32, f32), radius: f32, restitution: f32) -> bool {
+    let ab = (b.0 - a.0, b.1 - a.1);
+    let length2 = ab.0*ab.0 + ab.1*ab.1;
+    if length2 <= 0.0 {
+        return false;
+    }
+    let length = length2.sqrt();
+    let line_normal = (-ab.1 / length, ab.0 / length);
+
+    // Step 1: Did the center cross (or land right on) the segment's line, within the segment?
+    let side_now = (position.0 - a.0) * line_normal.0 + (position.1 - a.1) * line_normal.1;
+    let side_before = (previous.0 - a.0) * line_normal.0 + (previous.1 - a.1) * line_normal.1;
+    if side_before != 0.0 && side_now * side_before <= 0.0 {
+        let f = side_before / (side_before - side_now);
+        let crossing = (previous.0 + f * (position.0 - previous.0), previous.1 + f * (position.1 - previous.1));
+        let s = ((crossing.0 - a.0) * ab.0 + (crossing.1 - a.1) * ab.1) / length2;
+        if s >= 0.0 && s <= 1.0 {
+            // Put it back on the side it came from
+            let normal = if side_before > 0.0 { line_normal } else { (-line_normal.0, -line_normal.1) };
+            let push = side_now.abs() + radius;
+            let target = (position.0 + normal.0 * push, position.1 + normal.1 * push);
+            bounce(target, normal, position, last_position, restitution);
+            return true;
+        }
+    }
+
+    // Step 2: Overlapping the segment (or one of its end caps)?
+    let t = (((position.0 - a.0) * ab.0 + (position.1 - a.1) * ab.1) / length2).max(0.0).min(1.0);
+    let closest = (a.0 + t * ab.0, a.1 + t * ab.1);
+    let dx = position.0 - closest.0;
+    let dy = position.1 - closest.1;
+    let distance = (dx*dx + dy*dy).sqrt();
+    if distance < radius && distance > 0.0 {
+        let normal = (dx / distance, dy / distance);
+        bounce((closest.0 + normal.0 * radius, closest.1 + normal.1 * radius), normal, position, last_position, restitution);
+        return true;
+    }
+    return false;
+}
+
+// Helper function to resolve a particle against a solid disc
+// (swept like collide_segment, so a fast particle can't pass through a small disc in one substep)
+fn collide_circle(center: (f32, f32), circle_radius: f32, previous: (f32, f32), position: &mut (f32, f32), last_position: &mut (f32, f32), radius: f32, restitution: f32) -> bool {
+    let reach = circle_radius + radius;
+
+    // Step 1: Did the center enter the disc (grown by the particle's radius) since previous?
+    // (solve |previous + t * d - center| = reach for the first t in [0, 1])
+    let d = (position.0 - previous.0, position.1 - previous.1);
+    let f = (previous.0 - center.0, previous.1 - center.1);
+    let a = d.0*d.0 + d.1*d.1;
+    let b = f.0*d.0 + f.1*d.1;
+    let c = f.0*f.0 + f.1*f.1 - reach*reach;
+    if c > 0.0 && b < 0.0 && a > 0.0 {
+        let discriminant = b*b - a*c;
+        if discriminant >= 0.0 {
+            let t = (-b - discriminant.sqrt()) / a;
+            if t <= 1.0 {
+                // Put it back where it entered
+                let entry = (previous.0 + t * d.0, previous.1 + t * d.1);
+                let normal = ((entry.0 - center.0) / reach, (entry.1 - center.1) / reach);
+                bounce((center.0 + normal.0 * reach, center.1 + normal.1 * reach), normal, position, last_position, restitution);
+                return true;
+            }
+        }
+    }
+
+    // Step 2: Overlapping (e.g. was already overlapping at previous)?
+    let dx = position.0 - center.0;
+    let dy = position.1 - center.1;
+    let distance = (dx*dx + dy*dy).sqrt();
+    if distance < reach && distance > 0.0 {
+        let normal = (dx / distance, dy / distance);
+        bounce((center.0 + normal.0 * reach, center.1 + normal.1 * reach), normal, position, last_position, restitution);
+        return true;
+    }
+    return false;
+}
+
+// Helper function to move a particle to target and reflect its velocity off normal
+// (only if heading into the surface)
+fn bounce(target: (f32, f32), normal: (f32, f32), position: &mut (f32, f32), last_position: &mut (f32, f32), restitution: f32) {
+    let mut v = (position.0 - last_position.0, position.1 - last_position.1);
+    let vn = v.0 * normal.0 + v.1 * normal.1;
+    if vn < 0.0 {
+        v = (v.0 - (1.0 + restitution) * vn * normal.0, v.1 - (1.0 + restitution) * vn * normal.1);
+    }
+    *position = target;
+    *last_position = (target.0 - v.0, target.1 - v.1);
+}
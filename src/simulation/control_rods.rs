@@ -0,0 +1,51 @@
+use simulation::storage::ParticleId;
+
+// Rigid group of immovable (infinite mass) particles that moves as one unit
+// The rod sets its members' velocity every substep; contacts and forces can't move them
+pub struct ControlRod {
+    pub members: Vec<ParticleId>,
+    pub velocity: (f32, f32),   // In units/sec
+    pub offset: (f32, f32),     // Displacement from where the rod was added
+
+    // Travel limits on offset (e.g. fully inserted, fully withdrawn), per axis
+    pub min_offset: (f32, f32),
+    pub max_offset: (f32, f32),
+}
+
+// Create a stationary rod from its members (free to travel anywhere)
+pub fn create_control_rod(members: Vec<ParticleId>) -> ControlRod {
+    return ControlRod {
+        members: members,
+        velocity: (0.0, 0.0),
+        offset: (0.0, 0.0),
+        min_offset: (::std::f32::NEG_INFINITY, ::std::f32::NEG_INFINITY),
+        max_offset: (::std::f32::INFINITY, ::std::f32::INFINITY),
+    }
+}
+
+// Move a rod's offset by up to displacement, stopping at the ends of its travel
+// Returns how far it actually moved
+pub fn travel(rod: &mut ControlRod, displacement: (f32, f32)) -> (f32, f32) {
+    let target = (
+        (rod.offset.0 + displacement.0).max(rod.min_offset.0).min(rod.max_offset.0),
+        (rod.offset.1 + displacement.1).max(rod.min_offset.1).min(rod.max_offset.1),
+    );
+    let moved = (target.0 - rod.offset.0, target.1 - rod.offset.1);
+    rod.offset = target;
+    return moved;
+}
+
+// Centers for a line of touching circles from one point to another
+// (touching, so nothing the size of a neutron slips between them)
+pub fn rod_positions(from: (f32, f32), to: (f32, f32), radius: f32) -> Vec<(f32, f32)> {
+    let dx = to.0 - from.0;
+    let dy = to.1 - from.1;
+    let length = (dx*dx + dy*dy).sqrt();
+    let count = (length / (2.0 * radius)).ceil().max(1.0) as usize;
+    return (0..count + 1)
+        .map(|k| {
+            let f = k as f32 / count as f32;
+            (from.0 + f * dx, from.1 + f * dy)
+        })
+        .collect();
+}
@@ -0,0 +1,91 @@
+// Exchange between particles and a coolant (fluid grid), in world units
+
+// What the coolant is doing at a particle
+#[derive(Clone, Copy, Debug)]
+pub struct FluidSample {
+    pub velocity: (f32, f32),   // In units/sec
+    pub temperature: f32,
+}
+
+// What a particle hands the coolant (equal and opposite to what it took from it)
+#[derive(Clone, Copy, Debug)]
+pub struct FluidSource {
+    pub position: (f32, f32),
+    pub radius: f32,            // Spread over the cells under this disc
+    pub momentum: (f32, f32),
+    pub heat: f32,
+}
+
+// Coolant pump: pushes the coolant inside a disc
+// (applied as its own pass, not mixed in with the particle sources)
+#[derive(Clone, Copy, Debug)]
+pub struct Pump {
+    pub position: (f32, f32),
+    pub radius: f32,
+    pub force: (f32, f32),      // Momentum per sec, spread evenly over the cells under the disc
+}
+
+// Where a solver keeps the coolant temperature (above ambient), for drawing
+pub enum HeatMap<'a> {
+    Texture(u32),   // GL texture (x channel)
+    Grid { width: usize, height: usize, values: &'a [f32] },   // Row-major, bottom row first
+}
+
+// Coolant solver (GPU compute shaders in fluid, CPU grids in cpu_fluid)
+pub trait FluidSolver {
+    fn simulate(&mut self, dt: f32);
+    fn inject(&mut self, sources: &[FluidSource]);
+    fn pump(&mut self, pump: &Pump, dt: f32);
+    fn sample(&self, positions: &[(f32, f32)]) -> Vec<FluidSample>;
+    fn heat_map<'a>(&'a self) -> HeatMap<'a>;
+}
+
+// Velocity after dt of linear drag towards the coolant's velocity
+// (exact exponential relaxation, so it stays stable however strong the drag)
+pub fn drag(velocity: (f32, f32), fluid_velocity: (f32, f32), drag: f32, mass: f32, dt: f32) -> (f32, f32) {
+    let keep = (-drag / mass * dt).exp();
+    return (
+        fluid_velocity.0 + (velocity.0 - fluid_velocity.0) * keep,
+        fluid_velocity.1 + (velocity.1 - fluid_velocity.1) * keep,
+    );
+}
+
+// Temperature after dt of heat transfer towards the coolant's temperature
+// (heat_transfer = heat per sec per degree of difference, capacity = heat per degree)
+pub fn exchange_heat(temperature: f32, fluid_temperature: f32, heat_transfer: f32, capacity: f32, dt: f32) -> f32 {
+    let keep = (-heat_transfer / capacity * dt).exp();
+    return fluid_temperature + (temperature - fluid_temperature) * keep;
+}
+
+// Cells (x, y) whose centers are under a disc on a grid of size cells (center and radius in cells)
+// (a disc too small to cover any cell center gets the cell it's in; none if it's off the grid)
+pub fn cells_under_disc(center: (f32, f32), radius: f32, size: (usize, usize)) -> Vec<(usize, usize)> {
+    // Step 1: Skip discs off the grid
+    if center.0 < 0.0 || center.1 < 0.0 || center.0 >= size.0 as f32 || center.1 >= size.1 as f32 {
+        return vec![];
+    }
+
+    // Step 2: Find the cells under the disc
+    let lo = (clamp_cell(center.0 - radius, size.0), clamp_cell(center.1 - radius, size.1));
+    let hi = (clamp_cell((center.0 + radius).ceil(), size.0), clamp_cell((center.1 + radius).ceil(), size.1));
+    let mut cells = vec![];
+    for y in lo.1..hi.1 + 1 {
+        for x in lo.0..hi.0 + 1 {
+            let (dx, dy) = (x as f32 + 0.5 - center.0, y as f32 + 0.5 - center.1);
+            if dx * dx + dy * dy <= radius * radius {
+                cells.push((x, y));
+            }
+        }
+    }
+
+    // Step 3: Too small to cover a cell center? The cell it's in
+    if cells.is_empty() {
+        cells.push((center.0 as usize, center.1 as usize));
+    }
+    return cells;
+}
+
+// Helper function to clamp a coordinate (in cells) to a cell index
+fn clamp_cell(coord: f32, size: usize) -> usize {
+    return (coord.floor().max(0.0) as usize).min(size - 1);
+}
@@ -0,0 +1,282 @@
+// CPU implementation of the coolant grids (for testing without a GL context, and for contexts
+// without compute shaders; same advection as the compute shaders, but a sequential projection)
+
+use simulation::coupling::{FluidSample, FluidSolver, FluidSource, HeatMap, Pump, cells_under_disc};
+use simulation::fluid::{DEFAULT_DENSITY, DEFAULT_HEAT_CAPACITY, DEFAULT_VELOCITY_DAMPING, DEFAULT_HEAT_LOSS};
+use simulation::particles::AMBIENT_TEMPERATURE;
+
+// Projection sweeps per step
+const DEFAULT_PROJECTION_ITERATIONS: usize = 1;
+
+// Grids are row-major, bottom row first (cell (x, y) is at y * width + x)
+pub struct CpuFluid {
+    pub width: usize,
+    pub height: usize,
+    pub velocity_x: Vec<f32>,   // Coolant velocity (in cells/sec)
+    pub velocity_y: Vec<f32>,
+    pub temperature: Vec<f32>,  // Coolant temperature above ambient
+
+    cells_per_unit: (f32, f32),     // Grid cells per world unit
+
+    // Coolant properties
+    pub density: f32,
+    pub heat_capacity: f32,
+    pub velocity_damping: f32,
+    pub heat_loss: f32,
+    pub projection_iterations: usize,
+}
+
+// Create a CPU fluid covering the world with a grid of (width, height) cells
+pub fn create_cpu_fluid(world_size: (f32, f32), grid_size: (usize, usize)) -> CpuFluid {
+    let (width, height) = grid_size;
+    return CpuFluid {
+        width: width,
+        height: height,
+        velocity_x: vec![0.0; width * height],
+        velocity_y: vec![0.0; width * height],
+        temperature: vec![0.0; width * height],
+        cells_per_unit: (width as f32 / world_size.0, height as f32 / world_size.1),
+        density: DEFAULT_DENSITY,
+        heat_capacity: DEFAULT_HEAT_CAPACITY,
+        velocity_damping: DEFAULT_VELOCITY_DAMPING,
+        heat_loss: DEFAULT_HEAT_LOSS,
+        projection_iterations: DEFAULT_PROJECTION_ITERATIONS,
+    }
+}
+
+// Implement solver
+impl FluidSolver for CpuFluid {
+    fn simulate(&mut self, dt: f32) {
+        // Step 1: Advect velocity (both components along the old velocity)
+        let velocity_x = advect_field(self, &self.velocity_x, self.velocity_damping, dt);
+        let velocity_y = advect_field(self, &self.velocity_y, self.velocity_damping, dt);
+        self.velocity_x = velocity_x;
+        self.velocity_y = velocity_y;
+
+        // Step 2: Project velocity
+        for _ in 0..self.projection_iterations {
+            project_velocity(self);
+        }
+
+        // Step 3: Advect temperature
+        let temperature = advect_field(self, &self.temperature, self.heat_loss, dt);
+        self.temperature = temperature;
+    }
+
+    // Add particle momentum and heat to the grids (spread over each particle's disc)
+    fn inject(&mut self, sources: &[FluidSource]) {
+        let scale = self.cells_per_unit;
+        for source in sources {
+            // Step 1: Convert to grid units (velocity and temperature change, summed over the disc)
+            let center = (source.position.0 * scale.0, source.position.1 * scale.1);
+            let radius = (source.radius * scale.0).max(0.5);
+            let change = (
+                source.momentum.0 / self.density * scale.0,
+                source.momentum.1 / self.density * scale.1,
+                source.heat / (self.density * self.heat_capacity),
+            );
+
+            // Step 2: Find the cells under the disc (so the totals are conserved; none if off the grid)
+            let cells = cells_under_disc(center, radius, (self.width, self.height));
+
+            // Step 3: Spread the change evenly over the disc
+            let count = cells.len() as f32;
+            for (x, y) in cells {
+                let cell = y * self.width + x;
+                self.velocity_x[cell] += change.0 / count;
+                self.velocity_y[cell] += change.1 / count;
+                self.temperature[cell] += change.2 / count;
+            }
+        }
+    }
+
+    // Push the coolant under a pump's disc (force * dt, spread evenly)
+    fn pump(&mut self, pump: &Pump, dt: f32) {
+        self.inject(&[FluidSource {
+            position: pump.position,
+            radius: pump.radius,
+            momentum: (pump.force.0 * dt, pump.force.1 * dt),
+            heat: 0.0,
+        }]);
+    }
+
+    // Read the coolant velocity and temperature at world positions (bilinear)
+    fn sample(&self, positions: &[(f32, f32)]) -> Vec<FluidSample> {
+        let scale = self.cells_per_unit;
+        return positions.iter()
+            .map(|position| {
+                // Cell centers sit at +0.5
+                let coords = (position.0 * scale.0 - 0.5, position.1 * scale.1 - 0.5);
+                FluidSample {
+                    velocity: (
+                        sample_field(self, &self.velocity_x, coords) / scale.0,
+                        sample_field(self, &self.velocity_y, coords) / scale.1,
+                    ),
+                    temperature: AMBIENT_TEMPERATURE + sample_field(self, &self.temperature, coords),
+                }
+            })
+            .collect();
+    }
+
+    fn heat_map<'a>(&'a self) -> HeatMap<'a> {
+        return HeatMap::Grid { width: self.width, height: self.height, values: &self.temperature };
+    }
+}
+
+// Helper function to advect a field along the velocity (losing a fraction decay of it per sec)
+fn advect_field(sim: &CpuFluid, field: &[f32], decay: f32, dt: f32) -> Vec<f32> {
+    let keep = (-decay * dt).exp();
+    let mut advected = Vec::with_capacity(field.len());
+    for y in 0..sim.height {
+        for x in 0..sim.width {
+            // Read from where this cell's contents were dt ago (minus what decays)
+            let cell = y * sim.width + x;
+            let source = (x as f32 - sim.velocity_x[cell] * dt, y as f32 - sim.velocity_y[cell] * dt);
+            advected.push(sample_field(sim, field, source) * keep);
+        }
+    }
+    return advected;
+}
+
+// Helper function to project velocity (one Gauss-Seidel sweep)
+// (each cell's update sees the edges its neighbors already updated, unlike the compute shader's
+// single pass, where neighboring cells race on shared edges; so the two don't match cell for cell)
+fn project_velocity(sim: &mut CpuFluid) {
+    let width = sim.width;
+    for y in 0..sim.height {
+        for x in 0..width {
+            // Step 1: Take current edges (x = right edge, y = bottom edge; off the grid reads as 0)
+            let cell = y * width + x;
+            let left = if x > 0 { sim.velocity_x[cell - 1] } else { 0.0 };
+            let right = sim.velocity_x[cell];
+            let top = if y + 1 < sim.height { sim.velocity_y[cell + width] } else { 0.0 };
+            let bottom = sim.velocity_y[cell];
+
+            // Step 2: Calculate divergence
+            let d = right - left - bottom + top;
+
+            // Step 3: Update edges (writes off the grid are dropped)
+            if x > 0 {
+                sim.velocity_x[cell - 1] = left + d / 4.0;
+            }
+            sim.velocity_x[cell] = right - d / 4.0;
+            if y + 1 < sim.height {
+                sim.velocity_y[cell + width] = top - d / 4.0;
+            }
+            sim.velocity_y[cell] = bottom + d / 4.0;
+        }
+    }
+}
+
+// Helper function to sample a field at cell coords (bilinear between cell centers, clamped to the edge)
+fn sample_field(sim: &CpuFluid, field: &[f32], coords: (f32, f32)) -> f32 {
+    let x = coords.0.max(0.0).min((sim.width - 1) as f32);
+    let y = coords.1.max(0.0).min((sim.height - 1) as f32);
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(sim.width - 1), (y0 + 1).min(sim.height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let bottom = field[y0 * sim.width + x0] * (1.0 - fx) + field[y0 * sim.width + x1] * fx;
+    let top = field[y1 * sim.width + x0] * (1.0 - fx) + field[y1 * sim.width + x1] * fx;
+    return bottom * (1.0 - fy) + top * fy;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::prng::XorShiftRng;
+
+    // Helper function to measure divergence (sum of squares, with the same edges as project_velocity)
+    fn divergence(sim: &CpuFluid) -> f32 {
+        let width = sim.width;
+        let mut total = 0.0;
+        for y in 0..sim.height {
+            for x in 0..width {
+                let cell = y * width + x;
+                let left = if x > 0 { sim.velocity_x[cell - 1] } else { 0.0 };
+                let top = if y + 1 < sim.height { sim.velocity_y[cell + width] } else { 0.0 };
+                let d = sim.velocity_x[cell] - left - sim.velocity_y[cell] + top;
+                total += d * d;
+            }
+        }
+        return total;
+    }
+
+    #[test]
+    fn each_projection_sweep_reduces_divergence() {
+        let mut fluid = create_cpu_fluid((160.0, 120.0), (16, 12));
+        let mut rng = XorShiftRng::from_seed([7; 16]);
+        for cell in 0..fluid.width * fluid.height {
+            fluid.velocity_x[cell] = rng.gen_range(-5.0, 5.0);
+            fluid.velocity_y[cell] = rng.gen_range(-5.0, 5.0);
+        }
+
+        let mut before = divergence(&fluid);
+        for sweep in 0..20 {
+            project_velocity(&mut fluid);
+            let after = divergence(&fluid);
+            assert!(after < before, "sweep {} raised divergence from {} to {}", sweep, before, after);
+            before = after;
+        }
+    }
+
+    #[test]
+    fn inject_conserves_heat_and_momentum() {
+        let mut fluid = create_cpu_fluid((160.0, 120.0), (16, 12));
+        let sources = [
+            FluidSource { position: (80.0, 60.0), radius: 25.0, momentum: (3.0, -2.0), heat: 50.0 },
+            FluidSource { position: (2.0, 118.0), radius: 30.0, momentum: (-1.0, 4.0), heat: 20.0 },   // Disc hangs off the corner
+            FluidSource { position: (41.0, 33.0), radius: 0.1, momentum: (0.5, 0.5), heat: 7.0 },      // Smaller than a cell
+        ];
+        fluid.inject(&sources);
+
+        // Convert the grid totals back to world units
+        let scale = fluid.cells_per_unit;
+        let momentum = (
+            fluid.velocity_x.iter().sum::<f32>() * fluid.density / scale.0,
+            fluid.velocity_y.iter().sum::<f32>() * fluid.density / scale.1,
+        );
+        let heat = fluid.temperature.iter().sum::<f32>() * fluid.density * fluid.heat_capacity;
+        assert!((momentum.0 - 2.5).abs() < 1e-4 && (momentum.1 - 2.5).abs() < 1e-4, "momentum {:?}", momentum);
+        assert!((heat - 77.0).abs() < 1e-3, "heat {}", heat);
+    }
+
+    #[test]
+    fn advection_leaves_a_uniform_field_unchanged() {
+        let mut fluid = create_cpu_fluid((160.0, 120.0), (16, 12));
+        let mut rng = XorShiftRng::from_seed([3; 16]);
+        for cell in 0..fluid.width * fluid.height {
+            fluid.velocity_x[cell] = rng.gen_range(-40.0, 40.0);
+            fluid.velocity_y[cell] = rng.gen_range(-40.0, 40.0);
+        }
+
+        let field = vec![2.5; fluid.width * fluid.height];
+        let advected = advect_field(&fluid, &field, 0.0, 1.0 / 60.0);
+        for value in advected {
+            assert!((value - 2.5).abs() < 1e-5, "uniform field advected to {}", value);
+        }
+    }
+
+    #[test]
+    fn sample_is_bilinear() {
+        // A linear field is reproduced exactly between cell centers, and clamped past the edge ones
+        let mut fluid = create_cpu_fluid((160.0, 120.0), (16, 12));
+        for y in 0..fluid.height {
+            for x in 0..fluid.width {
+                fluid.temperature[y * fluid.width + x] = 2.0 * x as f32 + 3.0 * y as f32;
+                fluid.velocity_x[y * fluid.width + x] = x as f32;
+            }
+        }
+
+        let positions = [(5.0, 5.0), (37.0, 81.0), (123.4, 56.7), (155.0, 115.0), (0.0, 0.0), (200.0, 60.0)];
+        let samples = fluid.sample(&positions);
+        for (position, sample) in positions.iter().zip(samples) {
+            let cx = (position.0 * 0.1 - 0.5).max(0.0).min(15.0);
+            let cy = (position.1 * 0.1 - 0.5).max(0.0).min(11.0);
+            let expected = AMBIENT_TEMPERATURE + 2.0 * cx + 3.0 * cy;
+            assert!((sample.temperature - expected).abs() < 1e-3, "at {:?}: {} != {}", position, sample.temperature, expected);
+            assert!((sample.velocity.0 - cx / 0.1).abs() < 1e-2 && sample.velocity.1 == 0.0, "at {:?}: {:?}", position, sample.velocity);
+        }
+    }
+}
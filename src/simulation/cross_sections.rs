@@ -0,0 +1,214 @@
+// Cross section as a function of neutron kinetic energy (arbitrary area units)
+#[derive(Clone)]
+pub enum CrossSection {
+    // Same at every energy
+    Constant(f32),
+
+    // Falls off as 1/v: value at reference_energy, scaled by sqrt(reference_energy / energy)
+    OneOverV { value: f32, reference_energy: f32 },
+
+    // Linear interpolation between (energy, value) points, sorted by energy
+    // (clamped to the end values outside the table)
+    Table(Vec<(f32, f32)>),
+}
+
+// Cross sections of one material, one per reaction type
+#[derive(Clone)]
+pub struct CrossSections {
+    pub scatter: CrossSection,
+    pub capture: CrossSection,
+    pub fission: CrossSection,
+}
+
+// What a neutron does when it hits a nucleus
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Reaction {
+    Scatter,    // Bounces off (and may lose energy)
+    Capture,    // Absorbed, nothing comes out
+    Fission,    // Absorbed, splits the nucleus and releases new neutrons
+}
+
+// How many particles a reaction releases
+#[derive(Clone)]
+pub enum Multiplicity {
+    // Always this many
+    Fixed(usize),
+
+    // Probability of 0, 1, 2, ... (e.g. fission neutrons, mean nu ~ 2.4)
+    Table(Vec<f32>),
+}
+
+// Value of a cross section at a neutron energy
+pub fn evaluate(cross_section: &CrossSection, energy: f32) -> f32 {
+    return match *cross_section {
+        CrossSection::Constant(value) => value,
+        CrossSection::OneOverV { value, reference_energy } => {
+            if energy > 0.0 { value * (reference_energy / energy).sqrt() } else { value }
+        }
+        CrossSection::Table(ref points) => {
+            if points.is_empty() {
+                0.0
+            } else if energy <= points[0].0 {
+                points[0].1
+            } else {
+                let mut value = points[points.len() - 1].1;
+                for pair in points.windows(2) {
+                    let (e0, v0) = pair[0];
+                    let (e1, v1) = pair[1];
+                    if energy < e1 {
+                        value = if e1 > e0 { v0 + (v1 - v0) * (energy - e0) / (e1 - e0) } else { v1 };
+                        break;
+                    }
+                }
+                value
+            }
+        }
+    }
+}
+
+// Doppler feedback: how much hotter nuclei shrink the fission cross section
+// (1 at ambient temperature, (ambient / temperature)^doppler above it)
+pub fn doppler_factor(doppler: f32, ambient: f32, temperature: f32) -> f32 {
+    if doppler == 0.0 || !(temperature > 0.0) {
+        return 1.0;
+    }
+    return (ambient / temperature).powf(doppler);
+}
+
+// Pick a reaction with probability proportional to its cross section (u uniform in [0, 1))
+// (fission_scale scales the fission cross section, e.g. by doppler_factor)
+pub fn sample_reaction(cross_sections: &CrossSections, energy: f32, fission_scale: f32, u: f32) -> Reaction {
+    let scatter = evaluate(&cross_sections.scatter, energy).max(0.0);
+    let capture = evaluate(&cross_sections.capture, energy).max(0.0);
+    let fission = (evaluate(&cross_sections.fission, energy) * fission_scale).max(0.0);
+    let total = scatter + capture + fission;
+    if !(total > 0.0) {
+        return Reaction::Scatter;
+    }
+
+    let x = u * total;
+    if x < fission {
+        return Reaction::Fission;
+    } else if x < fission + capture {
+        return Reaction::Capture;
+    }
+    return Reaction::Scatter;
+}
+
+// Number of particles released (u uniform in [0, 1))
+pub fn sample_multiplicity(multiplicity: &Multiplicity, u: f32) -> usize {
+    return match *multiplicity {
+        Multiplicity::Fixed(count) => count,
+        Multiplicity::Table(ref probabilities) => {
+            let mut cumulative = 0.0;
+            let mut count = probabilities.len().saturating_sub(1);
+            for (n, p) in probabilities.iter().enumerate() {
+                cumulative += *p;
+                if u < cumulative {
+                    count = n;
+                    break;
+                }
+            }
+            count
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::prng::XorShiftRng;
+    use simulation::materials::{default_materials, find_material};
+
+    // Helper function to count each reaction over n draws
+    fn reaction_counts(cross_sections: &CrossSections, energy: f32, n: usize) -> (usize, usize, usize) {
+        let mut rng = XorShiftRng::from_seed([5; 16]);
+        let mut counts = (0, 0, 0);
+        for _ in 0..n {
+            match sample_reaction(cross_sections, energy, 1.0, rng.gen::<f32>()) {
+                Reaction::Scatter => counts.0 += 1,
+                Reaction::Capture => counts.1 += 1,
+                Reaction::Fission => counts.2 += 1,
+            }
+        }
+        return counts;
+    }
+
+    #[test]
+    fn fission_neutron_multiplicity_averages_2_4() {
+        let materials = default_materials();
+        let (fissile, neutron) = (find_material(&materials, "fissile").unwrap(), find_material(&materials, "neutron").unwrap());
+        let emission = materials[fissile.0].fission_products.iter().find(|e| e.particle_type == neutron).unwrap();
+
+        let mut rng = XorShiftRng::from_seed([1; 16]);
+        let n = 200_000;
+        let total: usize = (0..n).map(|_| sample_multiplicity(&emission.multiplicity, rng.gen::<f32>())).sum();
+        let mean = total as f32 / n as f32;
+        assert!((mean - 2.402).abs() < 0.01, "mean nu {}", mean);
+    }
+
+    #[test]
+    fn multiplicity_table_edges() {
+        let table = Multiplicity::Table(vec![0.25, 0.5, 0.25]);
+        assert_eq!(sample_multiplicity(&table, 0.0), 0);
+        assert_eq!(sample_multiplicity(&table, 0.3), 1);
+        assert_eq!(sample_multiplicity(&table, 0.999), 2);
+        assert_eq!(sample_multiplicity(&Multiplicity::Fixed(3), 0.7), 3);
+
+        // Probabilities that sum short of 1 put the rest on the last count
+        assert_eq!(sample_multiplicity(&Multiplicity::Table(vec![0.2, 0.2]), 0.9), 1);
+    }
+
+    #[test]
+    fn one_over_v_falls_with_speed() {
+        let cross_section = CrossSection::OneOverV { value: 4.0, reference_energy: 100.0 };
+        assert_eq!(evaluate(&cross_section, 100.0), 4.0);
+        assert!((evaluate(&cross_section, 400.0) - 2.0).abs() < 1e-6);
+        assert!((evaluate(&cross_section, 25.0) - 8.0).abs() < 1e-6);
+
+        // Capture and fission both fall as 1/v, so slow neutrons react more often than fast ones
+        let cross_sections = CrossSections {
+            scatter: CrossSection::Constant(1.0),
+            capture: CrossSection::Constant(0.0),
+            fission: cross_section,
+        };
+        let n = 100_000;
+        for &(energy, fission) in &[(25.0, 8.0), (400.0, 2.0)] {
+            let counts = reaction_counts(&cross_sections, energy, n);
+            let expected = fission / (1.0 + fission);
+            let fraction = counts.2 as f32 / n as f32;
+            assert!((fraction - expected).abs() < 0.01, "at {}: {} != {}", energy, fraction, expected);
+        }
+    }
+
+    #[test]
+    fn table_interpolates_and_clamps() {
+        let cross_section = CrossSection::Table(vec![(10.0, 1.0), (20.0, 3.0), (40.0, 2.0)]);
+        assert_eq!(evaluate(&cross_section, 5.0), 1.0);
+        assert_eq!(evaluate(&cross_section, 15.0), 2.0);
+        assert_eq!(evaluate(&cross_section, 20.0), 3.0);
+        assert_eq!(evaluate(&cross_section, 30.0), 2.5);
+        assert_eq!(evaluate(&cross_section, 100.0), 2.0);
+        assert_eq!(evaluate(&CrossSection::Table(vec![]), 10.0), 0.0);
+
+        // Sampled in proportion to the interpolated values
+        let cross_sections = CrossSections {
+            scatter: CrossSection::Constant(1.0),
+            capture: CrossSection::Table(vec![(10.0, 1.0), (20.0, 3.0)]),
+            fission: CrossSection::Constant(0.0),
+        };
+        let n = 100_000;
+        let counts = reaction_counts(&cross_sections, 15.0, n);
+        assert_eq!(counts.2, 0);
+        assert!((counts.1 as f32 / n as f32 - 2.0 / 3.0).abs() < 0.01, "{:?}", counts);
+    }
+
+    #[test]
+    fn doppler_shrinks_fission_when_hot() {
+        assert_eq!(doppler_factor(1.0, 293.0, 293.0), 1.0);
+        assert!((doppler_factor(1.0, 293.0, 586.0) - 0.5).abs() < 1e-6);
+        assert!((doppler_factor(0.5, 293.0, 1172.0) - 0.5).abs() < 1e-6);
+        assert_eq!(doppler_factor(0.0, 293.0, 1000.0), 1.0);
+    }
+}
@@ -1,29 +1,67 @@
 extern crate gl;
 
-use gl::types::GLuint;
+use std::ffi::c_void;
+use std::mem;
+
+use gl::types::*;
 
 use rendering::shaders;
 use rendering::textures;
+use simulation::coupling::{FluidSample, FluidSolver, FluidSource, HeatMap, Pump, cells_under_disc};
+use simulation::particles::AMBIENT_TEMPERATURE;
+
+// Default coolant properties (shared with the CPU solver)
+pub const DEFAULT_DENSITY: f32 = 1.0;           // Coolant mass per cell
+pub const DEFAULT_HEAT_CAPACITY: f32 = 1000.0;  // Heat per unit mass per degree
+pub const DEFAULT_VELOCITY_DAMPING: f32 = 0.5;  // Fraction of velocity lost per sec (stands in for viscosity)
+pub const DEFAULT_HEAT_LOSS: f32 = 0.2;         // Fraction of excess temperature removed per sec (heat exchanger)
+
+// Fixed-point units for the sum of every source's change (per channel; 2^30 leaves headroom in an i32)
+const FIXED_POINT_RANGE: f32 = 1073741824.0;
 
 pub struct Simulation {
-    pub mass: GLuint,
-    pub velocity: GLuint,
-    pub temp: GLuint,
+    pub mass: GLuint,       // Carried quantities: x = coolant temperature above ambient
+    pub velocity: GLuint,   // xy = coolant velocity (in cells/sec)
+    pub temp: GLuint,       // Scratch grid
     advect_field_comp: GLuint,
     project_velocity_comp: GLuint,
-}
+    inject_sources_comp: GLuint,
+    resolve_sources_comp: GLuint,
+    pump_comp: GLuint,
+    sample_points_comp: GLuint,
+
+    // Fixed-point source sums (velocity x, velocity y, temperature), added with image atomics
+    accumulators: [GLuint; 3],
+
+    // Shader storage for particle exchange
+    sources_buffer: GLuint,
+    points_buffer: GLuint,
+    samples_buffer: GLuint,
+
+    cells_per_unit: (f32, f32),     // Grid cells per world unit
 
-pub trait Simulatable {
-    fn simulate(&self);  // TODO: dt
+    // Coolant properties
+    pub density: f32,
+    pub heat_capacity: f32,
+    pub velocity_damping: f32,
+    pub heat_loss: f32,
 }
 
-// Create a simulation object
-pub fn create_simulation() -> Simulation {
+// Create a simulation object covering the world (grids are at screen resolution)
+pub fn create_simulation(world_size: (f32, f32)) -> Simulation {
     // Create advection compute shader
-    let advect_field_comp = shaders::build_compute(include_str!("../shaders/grids/advect_field.comp"));
+    let advect_field_comp = shaders::build_compute(include_str!("../../shaders/grids/advect_field.comp"));
 
     // Create projection compute shader
-    let project_velocity_comp = shaders::build_compute(include_str!("../shaders/grids/project_velocity.comp"));
+    let project_velocity_comp = shaders::build_compute(include_str!("../../shaders/grids/project_velocity.comp"));
+
+    // Create particle exchange compute shaders
+    let inject_sources_comp = shaders::build_compute(include_str!("../../shaders/grids/inject_sources.comp"));
+    let resolve_sources_comp = shaders::build_compute(include_str!("../../shaders/grids/resolve_sources.comp"));
+    let sample_points_comp = shaders::build_compute(include_str!("../../shaders/grids/sample_points.comp"));
+
+    // Create pump compute shader
+    let pump_comp = shaders::build_compute(include_str!("../../shaders/grids/pump.comp"));
 
     return Simulation {
         mass: textures::create_grid_texture(),
@@ -31,26 +69,237 @@ pub fn create_simulation() -> Simulation {
         temp: textures::create_grid_texture(),
         advect_field_comp: advect_field_comp,
         project_velocity_comp: project_velocity_comp,
+        inject_sources_comp: inject_sources_comp,
+        resolve_sources_comp: resolve_sources_comp,
+        pump_comp: pump_comp,
+        sample_points_comp: sample_points_comp,
+        accumulators: [
+            textures::create_accumulator_texture(),
+            textures::create_accumulator_texture(),
+            textures::create_accumulator_texture(),
+        ],
+        sources_buffer: create_buffer(),
+        points_buffer: create_buffer(),
+        samples_buffer: create_buffer(),
+        cells_per_unit: (crate::SCR_WIDTH as f32 / world_size.0, crate::SCR_HEIGHT as f32 / world_size.1),
+        density: DEFAULT_DENSITY,
+        heat_capacity: DEFAULT_HEAT_CAPACITY,
+        velocity_damping: DEFAULT_VELOCITY_DAMPING,
+        heat_loss: DEFAULT_HEAT_LOSS,
     }
 }
 
-// Implement simulation
-impl Simulatable for Simulation {
-    fn simulate(&self) {
-        advect_velocity(self, 1.0 / 60.0);
+// Implement solver
+impl FluidSolver for Simulation {
+    fn simulate(&mut self, dt: f32) {
+        advect_field(self, self.velocity, self.velocity_damping, dt);
         project_velocity(self);
+        advect_field(self, self.mass, self.heat_loss, dt);
+    }
+
+    // Add particle momentum and heat to the grids (spread over each particle's disc)
+    fn inject(&mut self, sources: &[FluidSource]) {
+        if sources.is_empty() {
+            return;
+        }
+
+        // Step 1: Convert to grid units (velocity and temperature change, summed over the disc)
+        let scale = self.cells_per_unit;
+        let mut data: Vec<f32> = Vec::with_capacity(8 * sources.len());
+        let mut total = [0.0f32; 3];
+        for source in sources {
+            let change = [
+                source.momentum.0 / self.density * scale.0,
+                source.momentum.1 / self.density * scale.1,
+                source.heat / (self.density * self.heat_capacity),
+            ];
+            data.extend_from_slice(&[
+                source.position.0 * scale.0,
+                source.position.1 * scale.1,
+                (source.radius * scale.0).max(0.5),
+                0.0,
+                change[0],
+                change[1],
+                change[2],
+                0.0,
+            ]);
+            for channel in 0..3 {
+                total[channel] += change[channel].abs();
+            }
+        }
+
+        // Step 2: Pick fixed-point scales (so even every source landing on one cell can't overflow)
+        let mut fixed_scale = [1.0f32; 3];
+        for channel in 0..3 {
+            if total[channel] > 0.0 {
+                fixed_scale[channel] = FIXED_POINT_RANGE / total[channel];
+            }
+        }
+
+        // Step 3: Sum them into the accumulators (image atomics, so overlapping sources don't race)
+        let uniform_fixed_scale = shaders::get_uniform_location(self.inject_sources_comp, "fixedScale");
+        unsafe {
+            // Set the fixed-point scales (the program must be in use first)
+            gl::UseProgram(self.inject_sources_comp);
+            gl::Uniform3f(uniform_fixed_scale, fixed_scale[0], fixed_scale[1], fixed_scale[2]);
+
+            // (0): sources read
+            upload_buffer(self.sources_buffer, &data);
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 0, self.sources_buffer);
+
+            // (1-3): accumulators read/write
+            for (unit, &accumulator) in self.accumulators.iter().enumerate() {
+                gl::BindImageTexture(1 + unit as GLuint, accumulator, 0, gl::FALSE, 0, gl::READ_WRITE, gl::R32I);
+            }
+
+            // Dispatch program (one invocation per source)
+            gl::DispatchCompute(sources.len() as u32, 1, 1);
+            gl::MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+        }
+
+        // Step 4: Add the sums to the grids (and clear the accumulators)
+        let uniform_fixed_scale = shaders::get_uniform_location(self.resolve_sources_comp, "fixedScale");
+        unsafe {
+            // Set the fixed-point scales (the program must be in use first)
+            gl::UseProgram(self.resolve_sources_comp);
+            gl::Uniform3f(uniform_fixed_scale, fixed_scale[0], fixed_scale[1], fixed_scale[2]);
+
+            // (0-2): accumulators read/write
+            for (unit, &accumulator) in self.accumulators.iter().enumerate() {
+                gl::BindImageTexture(unit as GLuint, accumulator, 0, gl::FALSE, 0, gl::READ_WRITE, gl::R32I);
+            }
+
+            // (3): velocity read/write
+            gl::BindImageTexture(3, self.velocity, 0, gl::FALSE, 0, gl::READ_WRITE, gl::RGBA32F);
+
+            // (4): mass read/write
+            gl::BindImageTexture(4, self.mass, 0, gl::FALSE, 0, gl::READ_WRITE, gl::RGBA32F);
+
+            // Dispatch program (one invocation per cell)
+            gl::DispatchCompute(crate::SCR_WIDTH, crate::SCR_HEIGHT, 1);
+            gl::MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT | gl::TEXTURE_FETCH_BARRIER_BIT);
+        }
+    }
+
+    // Push the coolant under a pump's disc (force * dt, spread evenly)
+    fn pump(&mut self, pump: &Pump, dt: f32) {
+        // Step 1: Find the cells under the disc (in grid units)
+        let scale = self.cells_per_unit;
+        let mut center = (pump.position.0 * scale.0, pump.position.1 * scale.1);
+        let mut radius = pump.radius * scale.0;
+        let cells = cells_under_disc(center, radius, (crate::SCR_WIDTH as usize, crate::SCR_HEIGHT as usize));
+        if cells.is_empty() {
+            return;
+        }
+
+        // Step 2: Too small to cover a cell center? Push just the cell it's in
+        if cells.len() == 1 {
+            center = (cells[0].0 as f32 + 0.5, cells[0].1 as f32 + 0.5);
+            radius = 0.0;
+        }
+
+        // Step 3: Spread the change evenly over the disc
+        let count = cells.len() as f32;
+        let change = (
+            pump.force.0 * dt / self.density * scale.0 / count,
+            pump.force.1 * dt / self.density * scale.1 / count,
+        );
+        let lo = (cells.iter().map(|cell| cell.0).min().unwrap(), cells.iter().map(|cell| cell.1).min().unwrap());
+        let hi = (cells.iter().map(|cell| cell.0).max().unwrap(), cells.iter().map(|cell| cell.1).max().unwrap());
+
+        // Step 4: Get uniforms
+        let uniform_origin = shaders::get_uniform_location(self.pump_comp, "origin");
+        let uniform_center = shaders::get_uniform_location(self.pump_comp, "center");
+        let uniform_radius = shaders::get_uniform_location(self.pump_comp, "radius");
+        let uniform_change = shaders::get_uniform_location(self.pump_comp, "change");
+
+        // Step 5: Push
+        unsafe {
+            // Set the disc and change (the program must be in use first)
+            gl::UseProgram(self.pump_comp);
+            gl::Uniform2i(uniform_origin, lo.0 as i32, lo.1 as i32);
+            gl::Uniform2f(uniform_center, center.0, center.1);
+            gl::Uniform1f(uniform_radius, radius);
+            gl::Uniform2f(uniform_change, change.0, change.1);
+
+            // (0): velocity read/write
+            gl::BindImageTexture(0, self.velocity, 0, gl::FALSE, 0, gl::READ_WRITE, gl::RGBA32F);
+
+            // Dispatch program (one invocation per cell of the disc's bounding box)
+            gl::DispatchCompute((hi.0 - lo.0 + 1) as u32, (hi.1 - lo.1 + 1) as u32, 1);
+            gl::MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT | gl::TEXTURE_FETCH_BARRIER_BIT);
+        }
+    }
+
+    // Read the coolant velocity and temperature at world positions
+    fn sample(&self, positions: &[(f32, f32)]) -> Vec<FluidSample> {
+        if positions.is_empty() {
+            return vec![];
+        }
+
+        // Step 1: Convert to grid units
+        let scale = self.cells_per_unit;
+        let mut points: Vec<f32> = Vec::with_capacity(4 * positions.len());
+        for position in positions {
+            points.extend_from_slice(&[position.0 * scale.0, position.1 * scale.1, 0.0, 0.0]);
+        }
+        let mut data: Vec<f32> = vec![0.0; 4 * positions.len()];
+
+        // Step 2: Sample on the GPU, then read the results back
+        unsafe {
+            // (0): points read
+            upload_buffer(self.points_buffer, &points);
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 0, self.points_buffer);
+
+            // (1): samples write
+            upload_buffer(self.samples_buffer, &data);
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 1, self.samples_buffer);
+
+            // (2): velocity read
+            gl::ActiveTexture(gl::TEXTURE2);
+            gl::BindTexture(gl::TEXTURE_2D, self.velocity);
+
+            // (3): mass read
+            gl::ActiveTexture(gl::TEXTURE3);
+            gl::BindTexture(gl::TEXTURE_2D, self.mass);
+
+            // Dispatch program (one invocation per point)
+            gl::UseProgram(self.sample_points_comp);
+            gl::DispatchCompute(positions.len() as u32, 1, 1);
+            gl::MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+
+            // Read back
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.samples_buffer);
+            gl::GetBufferSubData(gl::SHADER_STORAGE_BUFFER, 0, (data.len() * mem::size_of::<f32>()) as GLsizeiptr, data.as_mut_ptr() as *mut c_void);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+
+        // Step 3: Back to world units
+        return data.chunks(4)
+            .map(|sample| FluidSample {
+                velocity: (sample[0] / scale.0, sample[1] / scale.1),
+                temperature: AMBIENT_TEMPERATURE + sample[2],
+            })
+            .collect();
+    }
+
+    fn heat_map<'a>(&'a self) -> HeatMap<'a> {
+        return HeatMap::Texture(self.mass);
     }
 }
 
-// Helper function to advect velocity
-fn advect_velocity(sim: &Simulation, dt: f32) {
-    // Step 1: Get uniform
+// Helper function to advect a field along the velocity (losing a fraction decay of it per sec)
+fn advect_field(sim: &Simulation, field: GLuint, decay: f32, dt: f32) {
+    // Step 1: Get uniforms
     let uniform_dt = shaders::get_uniform_location(sim.advect_field_comp, "dt");
+    let uniform_decay = shaders::get_uniform_location(sim.advect_field_comp, "decay");
 
-    // Step 2: Advect velocity
+    // Step 2: Advect field
     unsafe {
-        // Set DT (todo: not 60FPS)
+        // Set DT and decay (the program must be in use first)
+        gl::UseProgram(sim.advect_field_comp);
         gl::Uniform1f(uniform_dt, dt);
+        gl::Uniform1f(uniform_decay, decay);
 
         // (0): velocity read
         gl::ActiveTexture(gl::TEXTURE0);
@@ -58,18 +307,17 @@ fn advect_velocity(sim: &Simulation, dt: f32) {
 
         // (1): field read
         gl::ActiveTexture(gl::TEXTURE1);
-        gl::BindTexture(gl::TEXTURE_2D, sim.velocity);
+        gl::BindTexture(gl::TEXTURE_2D, field);
 
-        // (2): velocity write
+        // (2): field write
         gl::BindImageTexture(2, sim.temp, 0, gl::FALSE, 0, gl::WRITE_ONLY, gl::RGBA32F);
 
         // Dispatch program
-        gl::UseProgram(sim.advect_field_comp);
         gl::DispatchCompute(crate::SCR_WIDTH, crate::SCR_HEIGHT, 1);
         gl::MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
 
-        // Copy temp -> velocity
-        textures::copy_grid_texture(sim.temp, sim.velocity);
+        // Copy temp -> field
+        textures::copy_grid_texture(sim.temp, field);
     }
 }
 
@@ -91,4 +339,18 @@ fn project_velocity(sim: &Simulation) {
         // Copy temp -> velocity
         textures::copy_grid_texture(sim.temp, sim.velocity);
     }
-}
\ No newline at end of file
+}
+
+// Helper function to create a shader storage buffer
+fn create_buffer() -> GLuint {
+    let mut id: GLuint = 0;
+    unsafe { gl::GenBuffers(1, &mut id) };
+    return id;
+}
+
+// Helper function to (re)fill a shader storage buffer
+unsafe fn upload_buffer(buffer: GLuint, data: &[f32]) {
+    gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buffer);
+    gl::BufferData(gl::SHADER_STORAGE_BUFFER, (data.len() * mem::size_of::<f32>()) as GLsizeiptr, data.as_ptr() as *const c_void, gl::DYNAMIC_DRAW);
+    gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+}
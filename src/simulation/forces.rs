@@ -0,0 +1,145 @@
+use simulation::particles::{ParticleType, G};
+
+// Axis-aligned region (for local fields)
+#[derive(Clone, Copy)]
+pub struct Region {
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+// What a force field sees of a particle
+pub struct ForceSample {
+    pub particle_type: ParticleType,
+    pub position: (f32, f32),
+    pub velocity: (f32, f32),   // In units/sec
+    pub mass: f32,
+}
+
+// Force field acting on particles
+// (region = None means everywhere)
+pub enum ForceField {
+    // Uniform acceleration (e.g. (0, -g))
+    Gravity { acceleration: (f32, f32), region: Option<Region> },
+
+    // Point mass pulling with G * mass / r^2 (negative mass repels)
+    // (softening keeps the pull finite near the point)
+    Attractor { position: (f32, f32), mass: f32, softening: f32 },
+
+    // Linear drag, F = -coefficient * v
+    Drag { coefficient: f32, region: Option<Region> },
+
+    // User-defined force (not acceleration) on a particle
+    Custom(Box<dyn Fn(&ForceSample) -> (f32, f32) + Send + Sync>),
+}
+
+// Helper function to check if a point is inside an optional region
+fn in_region(region: &Option<Region>, position: (f32, f32)) -> bool {
+    return match *region {
+        Some(ref r) => position.0 >= r.min.0 && position.0 < r.max.0 && position.1 >= r.min.1 && position.1 < r.max.1,
+        None => true,
+    }
+}
+
+// Acceleration a single field gives a particle
+pub fn field_acceleration(field: &ForceField, sample: &ForceSample) -> (f32, f32) {
+    // Immovable (infinite mass) particles don't accelerate
+    let inv_mass = 1.0 / sample.mass;
+    if !(inv_mass > 0.0) {
+        return (0.0, 0.0);
+    }
+
+    return match *field {
+        ForceField::Gravity { acceleration, ref region } => {
+            if in_region(region, sample.position) { acceleration } else { (0.0, 0.0) }
+        }
+        ForceField::Attractor { position, mass, softening } => {
+            let dx = position.0 - sample.position.0;
+            let dy = position.1 - sample.position.1;
+            let r2 = dx*dx + dy*dy + softening*softening;
+            let r = r2.sqrt();
+            if r > 0.0 {
+                let a = G * mass / r2;
+                (a * dx / r, a * dy / r)
+            } else {
+                (0.0, 0.0)
+            }
+        }
+        ForceField::Drag { coefficient, ref region } => {
+            if in_region(region, sample.position) {
+                (-coefficient * sample.velocity.0 * inv_mass, -coefficient * sample.velocity.1 * inv_mass)
+            } else {
+                (0.0, 0.0)
+            }
+        }
+        ForceField::Custom(ref force) => {
+            let f = force(sample);
+            (f.0 * inv_mass, f.1 * inv_mass)
+        }
+    }
+}
+
+// Total acceleration from every field
+pub fn total_acceleration(fields: &[ForceField], sample: &ForceSample) -> (f32, f32) {
+    let mut accel = (0.0, 0.0);
+    for field in fields {
+        let a = field_acceleration(field, sample);
+        accel.0 += a.0;
+        accel.1 += a.1;
+    }
+    return accel;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper function to make a sample of a particle
+    fn sample_at(position: (f32, f32), velocity: (f32, f32), mass: f32) -> ForceSample {
+        return ForceSample { particle_type: ParticleType(0), position: position, velocity: velocity, mass: mass };
+    }
+
+    #[test]
+    fn local_fields_only_act_inside_their_region() {
+        let region = Some(Region { min: (0.0, 0.0), max: (100.0, 100.0) });
+        let gravity = ForceField::Gravity { acceleration: (0.0, -9.8), region: region };
+        let drag = ForceField::Drag { coefficient: 2.0, region: region };
+
+        let inside = sample_at((50.0, 50.0), (10.0, -4.0), 4.0);
+        assert_eq!(field_acceleration(&gravity, &inside), (0.0, -9.8));
+        assert_eq!(field_acceleration(&drag, &inside), (-5.0, 2.0));
+
+        // (max edges are outside)
+        for &position in &[(150.0, 50.0), (100.0, 50.0), (50.0, -1.0)] {
+            let outside = sample_at(position, (10.0, -4.0), 4.0);
+            assert_eq!(field_acceleration(&gravity, &outside), (0.0, 0.0));
+            assert_eq!(field_acceleration(&drag, &outside), (0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn attractor_pulls_with_softened_inverse_square() {
+        let attractor = ForceField::Attractor { position: (0.0, 0.0), mass: 1.0e6, softening: 0.0 };
+        let near = field_acceleration(&attractor, &sample_at((10.0, 0.0), (0.0, 0.0), 1.0));
+        let far = field_acceleration(&attractor, &sample_at((0.0, 20.0), (0.0, 0.0), 50.0));
+        assert!((near.0 + G * 1.0e6 / 100.0).abs() < 1e-3 && near.1 == 0.0, "{:?}", near);
+        assert!((far.1 - near.0 / 4.0).abs() < 1e-3 && far.0 == 0.0, "{:?}", far);
+
+        // Softening caps the pull at the center, and negative mass repels
+        let soft = ForceField::Attractor { position: (0.0, 0.0), mass: -1.0e6, softening: 10.0 };
+        let a = field_acceleration(&soft, &sample_at((10.0, 0.0), (0.0, 0.0), 1.0));
+        assert!((a.0 - G * 1.0e6 / 200.0 * (10.0 / 200.0f32.sqrt())).abs() < 1e-3, "{:?}", a);
+        assert_eq!(field_acceleration(&soft, &sample_at((0.0, 0.0), (0.0, 0.0), 1.0)), (0.0, 0.0));
+    }
+
+    #[test]
+    fn custom_forces_divide_by_mass_and_fields_add_up() {
+        let fields = vec![
+            ForceField::Gravity { acceleration: (0.0, -10.0), region: None },
+            ForceField::Custom(Box::new(|sample: &ForceSample| (sample.position.0, 8.0))),
+        ];
+        assert_eq!(total_acceleration(&fields, &sample_at((6.0, 0.0), (0.0, 0.0), 2.0)), (3.0, -6.0));
+
+        // Immovable particles feel nothing
+        assert_eq!(total_acceleration(&fields, &sample_at((6.0, 0.0), (0.0, 0.0), ::std::f32::INFINITY)), (0.0, 0.0));
+    }
+}
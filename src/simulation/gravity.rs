@@ -0,0 +1,277 @@
+use simulation::particles::G;
+
+// Tree settings
+const LEAF_SIZE: usize = 4;
+const MAX_DEPTH: usize = 24;
+const NO_CHILD: usize = ::std::usize::MAX;
+
+// Mutual gravitation settings
+#[derive(Clone, Copy)]
+pub struct MutualGravity {
+    pub theta: f32,         // Opening angle (0 = exact O(n^2), larger = faster and rougher)
+    pub softening: f32,     // Keeps close encounters finite (in units)
+    pub constant: f32,      // Gravitational constant
+}
+
+// Create mutual gravitation settings using G
+pub fn create_mutual_gravity(theta: f32, softening: f32) -> MutualGravity {
+    return MutualGravity {
+        theta: theta,
+        softening: softening,
+        constant: G,
+    }
+}
+
+// Barnes-Hut quadtree node
+struct BhNode {
+    center_of_mass: (f32, f32),
+    mass: f32,
+    size: f32,              // Side length of the node's square
+    children: [usize; 4],   // Child per quadrant (NO_CHILD if empty)
+    leaf: bool,
+    start: usize,           // Bodies of this node: order[start..end]
+    end: usize,
+}
+
+// Barnes-Hut quadtree, rebuilt every substep
+pub struct BarnesHut {
+    nodes: Vec<BhNode>,
+    order: Vec<usize>,      // Body indices, grouped by node
+}
+
+// Create an empty tree
+pub fn create_barnes_hut() -> BarnesHut {
+    return BarnesHut {
+        nodes: vec![],
+        order: vec![],
+    }
+}
+
+pub trait GravityTree {
+    // Build the tree over every body with a finite, positive mass
+    fn build(&mut self, positions: &[(f32, f32)], masses: &[f32]);
+
+    // Gravitational acceleration on body i (or any point, with i = usize::MAX)
+    fn acceleration(&self, i: usize, position: (f32, f32), positions: &[(f32, f32)], masses: &[f32], gravity: &MutualGravity) -> (f32, f32);
+}
+
+impl GravityTree for BarnesHut {
+    fn build(&mut self, positions: &[(f32, f32)], masses: &[f32]) {
+        // Step 1: Gather bodies (immovable/massless ones don't attract)
+        self.nodes.clear();
+        self.order.clear();
+        for i in 0..positions.len() {
+            if masses[i] > 0.0 && masses[i].is_finite() {
+                self.order.push(i);
+            }
+        }
+        if self.order.is_empty() {
+            return;
+        }
+
+        // Step 2: Root = square bounding all bodies
+        let mut min = (::std::f32::MAX, ::std::f32::MAX);
+        let mut max = (::std::f32::MIN, ::std::f32::MIN);
+        for &i in &self.order {
+            min = (min.0.min(positions[i].0), min.1.min(positions[i].1));
+            max = (max.0.max(positions[i].0), max.1.max(positions[i].1));
+        }
+        let size = (max.0 - min.0).max(max.1 - min.1).max(1e-3);
+
+        // Step 3: Build top-down
+        let count = self.order.len();
+        build_node(self, min, size, 0, count, 0, positions, masses);
+    }
+
+    fn acceleration(&self, i: usize, position: (f32, f32), positions: &[(f32, f32)], masses: &[f32], gravity: &MutualGravity) -> (f32, f32) {
+        let mut accel = (0.0, 0.0);
+        if self.nodes.is_empty() {
+            return accel;
+        }
+
+        let eps2 = gravity.softening * gravity.softening;
+        let theta2 = gravity.theta * gravity.theta;
+        let mut stack = vec![0];
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            let dx = node.center_of_mass.0 - position.0;
+            let dy = node.center_of_mass.1 - position.1;
+            let d2 = dx*dx + dy*dy;
+
+            // Far enough away (size / distance < theta)? Treat the node as one body
+            if !node.leaf && node.size * node.size < theta2 * d2 {
+                add_pull(&mut accel, (dx, dy), d2 + eps2, gravity.constant * node.mass);
+                continue;
+            }
+
+            // Leaf: sum its bodies directly
+            if node.leaf {
+                for &j in &self.order[node.start..node.end] {
+                    if j == i {
+                        continue;
+                    }
+                    let dx = positions[j].0 - position.0;
+                    let dy = positions[j].1 - position.1;
+                    add_pull(&mut accel, (dx, dy), dx*dx + dy*dy + eps2, gravity.constant * masses[j]);
+                }
+                continue;
+            }
+
+            // Otherwise open it
+            for &child in &node.children {
+                if child != NO_CHILD {
+                    stack.push(child);
+                }
+            }
+        }
+
+        return accel;
+    }
+}
+
+// Helper function to add G*m*d/|d|^3 (softened)
+fn add_pull(accel: &mut (f32, f32), d: (f32, f32), r2: f32, gm: f32) {
+    if r2 <= 0.0 {
+        return;
+    }
+    let inv_r3 = 1.0 / (r2 * r2.sqrt());
+    accel.0 += gm * d.0 * inv_r3;
+    accel.1 += gm * d.1 * inv_r3;
+}
+
+// Helper function to build the node for order[start..end] (returns its index)
+fn build_node(tree: &mut BarnesHut, min: (f32, f32), size: f32, start: usize, end: usize, depth: usize, positions: &[(f32, f32)], masses: &[f32]) -> usize {
+    // Step 1: Mass and center of mass
+    let mut mass = 0.0;
+    let mut moment = (0.0, 0.0);
+    for &i in &tree.order[start..end] {
+        mass += masses[i];
+        moment.0 += masses[i] * positions[i].0;
+        moment.1 += masses[i] * positions[i].1;
+    }
+
+    let index = tree.nodes.len();
+    tree.nodes.push(BhNode {
+        center_of_mass: (moment.0 / mass, moment.1 / mass),
+        mass: mass,
+        size: size,
+        children: [NO_CHILD; 4],
+        leaf: true,
+        start: start,
+        end: end,
+    });
+
+    // Step 2: Small enough (or too deep, e.g. coincident bodies)? Leaf
+    if end - start <= LEAF_SIZE || depth >= MAX_DEPTH {
+        return index;
+    }
+
+    // Step 3: Partition bodies into quadrants (in place)
+    let half = 0.5 * size;
+    let mid = (min.0 + half, min.1 + half);
+    let quadrant = |i: usize| (if positions[i].0 >= mid.0 { 1 } else { 0 }) + (if positions[i].1 >= mid.1 { 2 } else { 0 });
+    tree.order[start..end].sort_by_key(|&i| quadrant(i));
+
+    // Step 4: Build non-empty children
+    tree.nodes[index].leaf = false;
+    let mut s = start;
+    for q in 0..4 {
+        let mut e = s;
+        while e < end && quadrant(tree.order[e]) == q {
+            e += 1;
+        }
+        if e > s {
+            let child_min = (if q & 1 == 1 { mid.0 } else { min.0 }, if q & 2 == 2 { mid.1 } else { min.1 });
+            let child = build_node(tree, child_min, half, s, e, depth + 1, positions, masses);
+            tree.nodes[index].children[q] = child;
+        }
+        s = e;
+    }
+
+    return index;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::prng::XorShiftRng;
+
+    // Helper function to scatter bodies in two clumps plus a sparse background (masses vary 100x)
+    fn bodies(n: usize) -> (Vec<(f32, f32)>, Vec<f32>) {
+        let mut rng = XorShiftRng::from_seed([9; 16]);
+        let mut positions = vec![];
+        let mut masses = vec![];
+        for k in 0..n {
+            let (center, spread) = match k % 4 {
+                0 => ((300.0, 300.0), 60.0),
+                1 => ((900.0, 500.0), 120.0),
+                _ => ((600.0, 400.0), 600.0),
+            };
+            positions.push((center.0 + rng.gen_range(-spread, spread), center.1 + rng.gen_range(-spread, spread)));
+            masses.push(rng.gen_range(1.0, 100.0));
+        }
+        return (positions, masses);
+    }
+
+    // Helper function for the exact O(n^2) acceleration on body i
+    fn direct_acceleration(i: usize, positions: &[(f32, f32)], masses: &[f32], gravity: &MutualGravity) -> (f64, f64) {
+        let eps2 = (gravity.softening * gravity.softening) as f64;
+        let mut accel = (0.0, 0.0);
+        for j in 0..positions.len() {
+            if j == i {
+                continue;
+            }
+            let dx = (positions[j].0 - positions[i].0) as f64;
+            let dy = (positions[j].1 - positions[i].1) as f64;
+            let r2 = dx*dx + dy*dy + eps2;
+            let gm = (gravity.constant * masses[j]) as f64;
+            accel = (accel.0 + gm * dx / (r2 * r2.sqrt()), accel.1 + gm * dy / (r2 * r2.sqrt()));
+        }
+        return accel;
+    }
+
+    // Helper function for the RMS error of the tree against direct summation (relative to the RMS acceleration)
+    fn relative_error(theta: f32) -> f64 {
+        let (positions, masses) = bodies(2000);
+        let gravity = create_mutual_gravity(theta, 2.0);
+        let mut tree = create_barnes_hut();
+        tree.build(&positions, &masses);
+
+        let (mut error, mut norm) = (0.0, 0.0);
+        for i in 0..positions.len() {
+            let exact = direct_acceleration(i, &positions, &masses, &gravity);
+            let approx = tree.acceleration(i, positions[i], &positions, &masses, &gravity);
+            let (ex, ey) = (approx.0 as f64 - exact.0, approx.1 as f64 - exact.1);
+            error += ex*ex + ey*ey;
+            norm += exact.0*exact.0 + exact.1*exact.1;
+        }
+        return (error / norm).sqrt();
+    }
+
+    #[test]
+    fn barnes_hut_matches_direct_summation() {
+        // theta = 0 opens every node (exact up to rounding); the monopole error grows about as theta^2
+        let mut last = 0.0;
+        for &theta in &[0.0, 0.25, 0.5, 0.75, 1.0] {
+            let error = relative_error(theta);
+            let tolerance = 1.0e-4 + 0.1 * (theta * theta) as f64;
+            assert!(error < tolerance, "theta {}: error {} over {}", theta, error, tolerance);
+            assert!(error >= last, "theta {}: error {} fell below {}", theta, error, last);
+            last = error;
+        }
+    }
+
+    #[test]
+    fn immovable_and_massless_bodies_do_not_attract() {
+        let positions = vec![(0.0, 0.0), (10.0, 0.0), (0.0, 10.0), (-10.0, 0.0)];
+        let masses = vec![1.0, 50.0, ::std::f32::INFINITY, 0.0];
+        let gravity = create_mutual_gravity(0.5, 0.0);
+        let mut tree = create_barnes_hut();
+        tree.build(&positions, &masses);
+
+        // Only the 50-mass body pulls on body 0
+        let accel = tree.acceleration(0, positions[0], &positions, &masses, &gravity);
+        assert!((accel.0 - G * 50.0 / 100.0).abs() < 1e-9 && accel.1 == 0.0, "{:?}", accel);
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use simulation::storage::ParticleId;
+
+// One neutron in the chain-reaction tree
+#[derive(Clone, Copy, Debug)]
+pub struct LineageNode {
+    pub id: ParticleId,             // Handle the neutron had while it lived
+    pub parent: Option<usize>,      // Node of the neutron whose fission released it (None = source neutron)
+    pub generation: u32,
+    pub birth_time: f32,            // Simulated time (in sec)
+}
+
+// Every neutron born while recording, in birth order (parents always come before their children)
+pub struct Lineage {
+    pub nodes: Vec<LineageNode>,
+    node_of: HashMap<ParticleId, usize>,    // Node of each recorded neutron
+    pub recording: bool,    // Off by default (the tree grows by a node per neutron, without bound)
+}
+
+// Create an empty tree (not recording)
+pub fn create_lineage() -> Lineage {
+    return Lineage {
+        nodes: vec![],
+        node_of: HashMap::new(),
+        recording: false,
+    }
+}
+
+// Record a neutron's birth if recording (parent = handle of the neutron that caused its fission)
+// (a parent born before recording started makes this a root)
+pub fn record_node(lineage: &mut Lineage, id: ParticleId, parent: Option<ParticleId>, generation: u32, birth_time: f32) {
+    if !lineage.recording {
+        return;
+    }
+    let parent = parent.and_then(|parent| lineage.node_of.get(&parent).cloned());
+    lineage.node_of.insert(id, lineage.nodes.len());
+    lineage.nodes.push(LineageNode {
+        id: id,
+        parent: parent,
+        generation: generation,
+        birth_time: birth_time,
+    });
+}
+
+// Forget every recorded neutron
+pub fn clear_lineage(lineage: &mut Lineage) {
+    lineage.nodes.clear();
+    lineage.node_of.clear();
+}
+
+// Export the tree as a Graphviz DOT digraph (one node per neutron, edges from parent to child)
+// (labeled with the generation and birth time, also kept as attributes for scripts)
+pub fn to_dot(lineage: &Lineage) -> String {
+    let mut dot = String::from("digraph lineage {\n    node [shape=box];\n");
+    for (n, node) in lineage.nodes.iter().enumerate() {
+        writeln!(dot, "    n{} [label=\"gen {}\\nt = {:.4} s\", generation={}, birth_time={}];",
+            n, node.generation, node.birth_time, node.generation, node.birth_time).unwrap();
+        if let Some(parent) = node.parent {
+            writeln!(dot, "    n{} -> n{};", parent, n).unwrap();
+        }
+    }
+    dot.push_str("}\n");
+    return dot;
+}
+
+// Export the tree as JSON: {"neutrons": [{"node", "parent", "generation", "birth_time"}, ...]}
+// (parent is a node index, or null for source neutrons)
+pub fn to_json(lineage: &Lineage) -> String {
+    let mut json = String::from("{\"neutrons\": [");
+    for (n, node) in lineage.nodes.iter().enumerate() {
+        let parent = match node.parent {
+            Some(parent) => parent.to_string(),
+            None => String::from("null"),
+        };
+        let separator = if n == 0 { "" } else { "," };
+        write!(json, "{}\n  {{\"node\": {}, \"parent\": {}, \"generation\": {}, \"birth_time\": {}}}",
+            separator, n, parent, node.generation, node.birth_time).unwrap();
+    }
+    json.push_str("\n]}\n");
+    return json;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot_nodes_show_generation_and_birth_time() {
+        let mut lineage = create_lineage();
+        lineage.recording = true;
+        let (a, b) = (ParticleId { slot: 0, generation: 0 }, ParticleId { slot: 1, generation: 0 });
+        record_node(&mut lineage, a, None, 0, 0.0);
+        record_node(&mut lineage, b, Some(a), 1, 0.25);
+
+        let dot = to_dot(&lineage);
+        assert!(!dot.contains("shape=point"));
+        assert!(dot.contains("n0 [label=\"gen 0\\nt = 0.0000 s\", generation=0, birth_time=0];"), "{}", dot);
+        assert!(dot.contains("n1 [label=\"gen 1\\nt = 0.2500 s\", generation=1, birth_time=0.25];"), "{}", dot);
+        assert!(dot.contains("n0 -> n1;"));
+    }
+}
@@ -0,0 +1,303 @@
+use std::fs;
+
+use toml;
+
+use simulation::particles::ParticleType;
+use simulation::cross_sections::{CrossSection, CrossSections, Multiplicity, Reaction};
+
+// Built-in registry
+const DEFAULT_MATERIALS: &'static str = include_str!("../../materials.toml");
+
+// Material properties shared by every particle of a type
+// (loaded from a registry file, see materials.toml)
+#[derive(Clone)]
+pub struct Material {
+    pub name: String,
+    pub color: (f32, f32, f32, f32),
+    pub radius: f32,        // Default radius of new particles
+    pub mass: f32,          // Default mass of new particles (infinite = immovable)
+    pub restitution: f32,   // Coefficient of restitution (1 = elastic, 0 = perfectly inelastic)
+    pub neutron: bool,      // Triggers reactions in what it hits
+    pub energy_loss: f32,   // Fraction of a neutron's kinetic energy removed per scatter off this material
+    pub wear: Wear,         // Inventory lost per reaction
+    pub energy_release: EnergyRelease,  // Energy released per event (deposited as heat)
+    pub heat_capacity: f32, // Heat per unit mass per degree (specific heat)
+
+    // Temperature feedback
+    pub cooling_rate: f32,  // Conduction to the surroundings (fraction of excess temperature lost per sec)
+    pub doppler: f32,       // Fission cross section scales by (ambient / temperature)^doppler
+    pub thermal_expansion: f32,     // Radius grows by this fraction per degree above ambient
+
+    // Coolant coupling
+    pub drag: f32,          // Drag force per unit of velocity relative to the coolant
+    pub heat_transfer: f32, // Heat exchanged with the coolant per sec per degree of difference
+    pub cross_sections: CrossSections,  // Odds of each reaction when a neutron hits
+    pub fission_products: Vec<Emission>,
+    pub detonation_products: Vec<Emission>,
+
+    // Radioactive decay
+    pub half_life: f32,     // In sec (infinite = stable)
+    pub decay_into: Option<ParticleType>,   // What a decayed particle becomes (None = gone)
+    pub decay_products: Vec<Emission>,      // Released by each decay (e.g. delayed neutrons)
+}
+
+// Inventory lost per reaction (inventory starts at 1.0 = fresh, despawned below 0.0)
+#[derive(Clone, Copy)]
+pub struct Wear {
+    pub scatter: f32,
+    pub capture: f32,
+    pub fission: f32,
+}
+
+// Energy released per event, in simulation units
+#[derive(Clone, Copy)]
+pub struct EnergyRelease {
+    pub capture: f32,
+    pub fission: f32,
+    pub decay: f32,
+}
+
+// Particles a reaction releases
+#[derive(Clone)]
+pub struct Emission {
+    pub particle_type: ParticleType,
+    pub multiplicity: Multiplicity,
+    pub momentum: f32,      // Random momentum range (see Simulatable::random_momentum)
+}
+
+// Registry file layout
+#[derive(Deserialize)]
+struct RegistryConfig {
+    material: Vec<MaterialConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialConfig {
+    name: String,
+    #[serde(default = "default_color")]
+    color: (f32, f32, f32, f32),
+    #[serde(default = "default_radius")]
+    radius: f32,
+    #[serde(default = "default_mass")]
+    mass: f32,
+    #[serde(default = "default_restitution")]
+    restitution: f32,
+    #[serde(default)]
+    neutron: bool,
+    #[serde(default)]
+    energy_loss: f32,
+    #[serde(default)]
+    wear: WearConfig,
+    #[serde(default)]
+    energy_release: EnergyReleaseConfig,
+    #[serde(default = "default_heat_capacity")]
+    heat_capacity: f32,
+    #[serde(default)]
+    cooling_rate: f32,
+    #[serde(default)]
+    doppler: f32,
+    #[serde(default)]
+    thermal_expansion: f32,
+    #[serde(default)]
+    drag: f32,
+    #[serde(default)]
+    heat_transfer: f32,
+    #[serde(default)]
+    cross_sections: CrossSectionsConfig,
+    #[serde(default)]
+    fission_products: Vec<EmissionConfig>,
+    #[serde(default)]
+    detonation_products: Vec<EmissionConfig>,
+    #[serde(default = "default_half_life")]
+    half_life: f32,
+    #[serde(default)]
+    decay_into: Option<String>,
+    #[serde(default)]
+    decay_products: Vec<EmissionConfig>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct WearConfig {
+    scatter: f32,
+    capture: f32,
+    fission: f32,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct EnergyReleaseConfig {
+    capture: f32,
+    fission: f32,
+    decay: f32,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct CrossSectionsConfig {
+    scatter: CrossSectionConfig,
+    capture: CrossSectionConfig,
+    fission: CrossSectionConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CrossSectionConfig {
+    Constant(f32),
+    OneOverV { one_over_v: f32, reference_energy: f32 },
+    Table(Vec<(f32, f32)>),
+}
+
+impl Default for CrossSectionConfig {
+    fn default() -> CrossSectionConfig {
+        return CrossSectionConfig::Constant(0.0);
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EmissionConfig {
+    particle: String,
+    multiplicity: MultiplicityConfig,
+    momentum: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MultiplicityConfig {
+    Fixed(usize),
+    Table(Vec<f32>),
+}
+
+fn default_color() -> (f32, f32, f32, f32) { (1.0, 1.0, 1.0, 1.0) }
+fn default_radius() -> f32 { 4.0 }
+fn default_mass() -> f32 { 1.0 }
+fn default_restitution() -> f32 { 1.0 }
+fn default_heat_capacity() -> f32 { 1.0 }
+fn default_half_life() -> f32 { ::std::f32::INFINITY }
+
+// Built-in materials, indexed by particle type
+pub fn default_materials() -> Vec<Material> {
+    return parse_materials(DEFAULT_MATERIALS).expect("Built-in materials.toml is invalid");
+}
+
+// Load a material registry file
+pub fn load_materials(path: &str) -> Result<Vec<Material>, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_materials(&source).map_err(|e| format!("{}: {}", path, e));
+}
+
+// Parse a material registry (TOML), resolving product names to particle types
+pub fn parse_materials(source: &str) -> Result<Vec<Material>, String> {
+    let config: RegistryConfig = toml::from_str(source).map_err(|e| e.to_string())?;
+
+    // Step 1: Names -> particle types (file order)
+    let names: Vec<String> = config.material.iter().map(|m| m.name.clone()).collect();
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].contains(name) {
+            return Err(format!("material \"{}\" is defined twice", name));
+        }
+    }
+
+    // Step 2: Build materials
+    let mut materials = vec![];
+    for m in config.material {
+        let fission_products = resolve_emissions(&m.fission_products, &names)?;
+        let detonation_products = resolve_emissions(&m.detonation_products, &names)?;
+        let decay_products = resolve_emissions(&m.decay_products, &names)?;
+        let decay_into = match m.decay_into {
+            Some(ref name) => Some(resolve_name(name, &names)?),
+            None => None,
+        };
+        materials.push(Material {
+            name: m.name,
+            color: m.color,
+            radius: m.radius,
+            mass: m.mass,
+            restitution: m.restitution,
+            neutron: m.neutron,
+            energy_loss: m.energy_loss,
+            wear: Wear {
+                scatter: m.wear.scatter,
+                capture: m.wear.capture,
+                fission: m.wear.fission,
+            },
+            energy_release: EnergyRelease {
+                capture: m.energy_release.capture,
+                fission: m.energy_release.fission,
+                decay: m.energy_release.decay,
+            },
+            heat_capacity: m.heat_capacity,
+            cooling_rate: m.cooling_rate,
+            doppler: m.doppler,
+            thermal_expansion: m.thermal_expansion,
+            drag: m.drag,
+            heat_transfer: m.heat_transfer,
+            cross_sections: CrossSections {
+                scatter: resolve_cross_section(m.cross_sections.scatter),
+                capture: resolve_cross_section(m.cross_sections.capture),
+                fission: resolve_cross_section(m.cross_sections.fission),
+            },
+            fission_products: fission_products,
+            detonation_products: detonation_products,
+            half_life: m.half_life,
+            decay_into: decay_into,
+            decay_products: decay_products,
+        });
+    }
+
+    return Ok(materials);
+}
+
+// Particle type of a material by name
+pub fn find_material(materials: &[Material], name: &str) -> Option<ParticleType> {
+    return materials.iter().position(|m| m.name == name).map(ParticleType);
+}
+
+// Inventory a material loses to a reaction
+pub fn wear(material: &Material, reaction: Reaction) -> f32 {
+    return match reaction {
+        Reaction::Scatter => material.wear.scatter,
+        Reaction::Capture => material.wear.capture,
+        Reaction::Fission => material.wear.fission,
+    }
+}
+
+// Restitution of a contact between two materials (the less bouncy one wins)
+pub fn combined_restitution(a: &Material, b: &Material) -> f32 {
+    return a.restitution.min(b.restitution);
+}
+
+// Helper function to convert a configured cross section
+fn resolve_cross_section(config: CrossSectionConfig) -> CrossSection {
+    return match config {
+        CrossSectionConfig::Constant(value) => CrossSection::Constant(value),
+        CrossSectionConfig::OneOverV { one_over_v, reference_energy } => CrossSection::OneOverV { value: one_over_v, reference_energy: reference_energy },
+        CrossSectionConfig::Table(points) => CrossSection::Table(points),
+    }
+}
+
+// Helper function to resolve a material name to its particle type
+fn resolve_name(name: &str, names: &[String]) -> Result<ParticleType, String> {
+    return match names.iter().position(|n| n == name) {
+        Some(index) => Ok(ParticleType(index)),
+        None => Err(format!("unknown material \"{}\"", name)),
+    }
+}
+
+// Helper function to resolve configured products by material name
+fn resolve_emissions(configs: &[EmissionConfig], names: &[String]) -> Result<Vec<Emission>, String> {
+    let mut emissions = vec![];
+    for config in configs {
+        emissions.push(Emission {
+            particle_type: resolve_name(&config.particle, names)?,
+            multiplicity: match config.multiplicity {
+                MultiplicityConfig::Fixed(count) => Multiplicity::Fixed(count),
+                MultiplicityConfig::Table(ref probabilities) => Multiplicity::Table(probabilities.clone()),
+            },
+            momentum: config.momentum,
+        });
+    }
+    return Ok(emissions);
+}
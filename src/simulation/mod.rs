@@ -1 +1,16 @@
-pub mod particles;
\ No newline at end of file
+pub mod boundaries;
+pub mod broadphase;
+pub mod colliders;
+pub mod control_rods;
+pub mod coupling;
+pub mod cpu_fluid;
+pub mod cross_sections;
+pub mod fluid;
+pub mod forces;
+pub mod gravity;
+pub mod lineage;
+pub mod materials;
+pub mod neutrons;
+pub mod particles;
+pub mod stats;
+pub mod storage;
\ No newline at end of file
@@ -0,0 +1,78 @@
+// Neutron speeds (in units/sec)
+const THERMAL_SPEED: f32 = 300.0;       // Moderation slows neutrons down to this, no further
+const EPITHERMAL_SPEED: f32 = 600.0;    // Slower = thermal
+const FAST_SPEED: f32 = 2000.0;         // Faster = fast
+
+// Neutron energy group
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EnergyGroup {
+    Thermal,
+    Epithermal,
+    Fast,
+}
+
+// Kinetic energy, 1/2 m v^2
+pub fn kinetic_energy(mass: f32, velocity: (f32, f32)) -> f32 {
+    return 0.5 * mass * (velocity.0*velocity.0 + velocity.1*velocity.1);
+}
+
+// Energy group of a particle with this kinetic energy and mass
+pub fn energy_group(energy: f32, mass: f32) -> EnergyGroup {
+    let speed = (2.0 * energy / mass).sqrt();
+    if speed < EPITHERMAL_SPEED {
+        return EnergyGroup::Thermal;
+    } else if speed < FAST_SPEED {
+        return EnergyGroup::Epithermal;
+    }
+    return EnergyGroup::Fast;
+}
+
+// Velocity after one scatter that removes energy_loss (a fraction) of the kinetic energy
+// (never slower than thermal; already-thermal neutrons are left alone)
+pub fn moderate(velocity: (f32, f32), energy_loss: f32) -> (f32, f32) {
+    let speed = (velocity.0*velocity.0 + velocity.1*velocity.1).sqrt();
+    if speed <= THERMAL_SPEED {
+        return velocity;
+    }
+    let scale = (speed * (1.0 - energy_loss).max(0.0).sqrt()).max(THERMAL_SPEED) / speed;
+    return (velocity.0 * scale, velocity.1 * scale);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper function for the speed of a velocity
+    fn speed(velocity: (f32, f32)) -> f32 {
+        return (velocity.0*velocity.0 + velocity.1*velocity.1).sqrt();
+    }
+
+    #[test]
+    fn energy_groups_split_at_the_speed_thresholds() {
+        let group = |speed: f32| energy_group(kinetic_energy(2.0, (0.0, speed)), 2.0);
+        assert_eq!(group(0.0), EnergyGroup::Thermal);
+        assert_eq!(group(EPITHERMAL_SPEED - 1.0), EnergyGroup::Thermal);
+        assert_eq!(group(EPITHERMAL_SPEED + 1.0), EnergyGroup::Epithermal);
+        assert_eq!(group(FAST_SPEED - 1.0), EnergyGroup::Epithermal);
+        assert_eq!(group(FAST_SPEED + 1.0), EnergyGroup::Fast);
+    }
+
+    #[test]
+    fn moderation_removes_energy_down_to_thermal() {
+        // Half the energy gone: speed / sqrt(2), same direction
+        let v = moderate((3000.0, -4000.0), 0.5);
+        assert!((speed(v) - 5000.0 / 2.0f32.sqrt()).abs() < 0.1, "{:?}", v);
+        assert!((v.0 / v.1 + 0.75).abs() < 1e-5);
+
+        // Never below thermal, and thermal neutrons are left alone
+        assert!((speed(moderate((400.0, 0.0), 0.9)) - THERMAL_SPEED).abs() < 1e-3);
+        assert_eq!(moderate((0.0, 200.0), 0.5), (0.0, 200.0));
+
+        // Repeated scatters thermalize a fast neutron
+        let mut v = (12000.0, 0.0);
+        for _ in 0..20 {
+            v = moderate(v, 0.5);
+        }
+        assert_eq!(energy_group(kinetic_energy(1.0, v), 1.0), EnergyGroup::Thermal);
+    }
+}
@@ -1,360 +1,1628 @@
 extern crate gl;
 
-use glfw::ffi::glfwGetTime;
+use rand::{Rng, SeedableRng};
+use rand::prng::XorShiftRng;
 
-const G: f32 = 0.001;
+use std::collections::HashSet;
 
-// Enum for particle type
-#[derive(Clone, Copy, PartialEq)]
-pub enum ParticleType {
-    Neutron,
-    Fissile,
-    Reflector,
-    StarterCap
-}
+use rayon::prelude::*;
+
+use simulation::broadphase::{Broadphase, BroadphaseKind, create_broadphase};
+use simulation::storage::{Particles, ParticleId, ParticleStorage, create_particles};
+use simulation::materials::{Emission, Material, combined_restitution, default_materials, find_material, wear};
+use simulation::forces::{ForceField, ForceSample, total_acceleration};
+use simulation::gravity::{BarnesHut, GravityTree, MutualGravity, create_barnes_hut};
+use simulation::boundaries::{Boundaries, Boundary, apply_boundaries, create_boundaries, periodic_images, separation};
+use simulation::colliders::{Collider, collide};
+use simulation::neutrons::{EnergyGroup, energy_group, kinetic_energy, moderate};
+use simulation::cross_sections::{Reaction, doppler_factor, sample_multiplicity, sample_reaction};
+use simulation::control_rods::{ControlRod, create_control_rod, rod_positions, travel};
+use simulation::stats::{Stats, create_stats, record, record_birth, record_sample};
+use simulation::lineage::{Lineage, clear_lineage, create_lineage, record_node};
+use simulation::coupling::{FluidSample, FluidSolver, FluidSource, Pump, drag, exchange_heat};
+
+// Gravitational constant
+pub const G: f32 = 0.001;
+
+// Temperature of newly spawned particles
+pub const AMBIENT_TEMPERATURE: f32 = 293.0;
+
+// Default timestep settings
+const DEFAULT_FIXED_DT: f32 = 1.0 / 60.0;
+const DEFAULT_SUBSTEPS: usize = 8;
+const DEFAULT_MAX_STEPS: usize = 4;
+
+// Max passes over the static colliders per substep
+// (a particle pushed out of one segment can end up behind another, e.g. in a corner)
+const STATIC_COLLIDER_PASSES: usize = 4;
+
+// Default world extent (in units)
+const DEFAULT_WORLD_SIZE: (f32, f32) = (1920.0, 1080.0);
+
+// Particle type: index into the material registry (Simulation::materials)
+// (look types up by material name with find_material; indices follow the registry's file order)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ParticleType(pub usize);
 
 // Particle data struct
+// (a single particle by value; the simulation stores them struct-of-arrays, see storage.rs)
 #[derive(Clone, Copy)]
 pub struct Particle {
     pub position: (f32, f32),
-    last_position: (f32, f32),
-    acceleration: (f32, f32),
-    pub mass: f32,  // For now, mass = radius
+    pub last_position: (f32, f32),
+    pub acceleration: (f32, f32),
+    pub radius: f32,        // Collision radius
+    pub mass: f32,          // Physical mass
+    pub inventory: f32,     // Fuel/material left (1.0 = fresh, despawned below 0.0)
+    pub temperature: f32,   // Raised by heat deposited into the particle
+    pub generation: u32,    // Neutron generation (0 = source, +1 per fission; products inherit it)
+    pub parent: Option<ParticleId>,     // Neutron whose fission released it (None = source)
+    pub birth_time: f32,    // Simulated time it was spawned (in sec)
     pub particle_type: ParticleType,
 }
 
 pub struct Simulation {
-    pub particles: Vec<Particle>,
-    grid_res: usize,
-    last_t: f64,
+    pub particles: Particles,
+    broadphase: Box<dyn Broadphase>,
+    rng: XorShiftRng,  // Every random draw goes through here (so a seed reproduces a run)
+    materials: Vec<Material>,
+    force_fields: Vec<ForceField>,
+    mutual_gravity: Option<MutualGravity>,   // Barnes-Hut N-body gravitation (off = None)
+    gravity_tree: BarnesHut,
+    boundaries: Boundaries,
+    colliders: Vec<Collider>,
+    control_rods: Vec<ControlRod>,
+    stats: Stats,
+    lineage: Lineage,   // Chain-reaction tree (every neutron born while recording)
+    coolant: Option<Box<dyn FluidSolver>>,  // Coupled once per fixed step (None = no coolant)
+    pumps: Vec<Pump>,
+    decay_heat: Vec<FluidSource>,   // Decay heat waiting to be handed to the coolant
+
+    // Fixed timestep
+    fixed_dt: f32,      // Physics step (in sec)
+    substeps: usize,    // Substeps per physics step
+    max_steps: usize,   // Max physics steps per call to step (avoids a spiral of death)
+    accumulator: f32,   // Time not yet simulated (in sec)
+
+    // Run the broadphase, narrowphase, forces and integration on the rayon pool?
+    // (same results as serial: pairs and contacts are found in parallel, then contacts are
+    // applied in pair order, since applying them moves particles and draws random numbers)
+    parallel: bool,
+}
+
+// Contact found by the narrowphase
+struct Contact {
+    i: usize,
+    j: usize,
+    depth: f32,             // Penetration depth
+    normal: (f32, f32),     // Points from j towards i
 }
 
 pub trait Simulatable {
     // Simulation steps
-    fn simulate(&mut self, cull: bool, detonate: bool);
+    // (dt is the caller's time delta in sec, so the sim never reads a clock itself)
+    fn step(&mut self, dt: f32, detonate: bool);
+    fn advance(&mut self);
+    fn drive_control_rods(&mut self);
+    fn decay(&mut self);
+    fn cool(&mut self);
+    fn apply_boundaries(&mut self);
+    fn resolve_static_collisions(&mut self);
+    fn accumulate_forces(&mut self);
     fn integrate(&mut self, dt: f32);
-    fn construct_grid(&self) -> Vec<Vec<usize>>;
-    fn resolve_collisions(&mut self, grid: &Vec<Vec<usize>>);
+    fn find_pairs(&mut self) -> Vec<(usize, usize)>;
+    fn resolve_collisions(&mut self, pairs: &Vec<(usize, usize)>);
+    fn couple_coolant(&mut self);
+
+    // Timestep settings
+    fn set_timestep(&mut self, fixed_dt: f32, substeps: usize);
+    fn set_max_steps(&mut self, max_steps: usize);
+    fn substep_dt(&self) -> f32;
+
+    // Materials
+    fn material(&self, particle_type: ParticleType) -> &Material;
+    fn set_material(&mut self, particle_type: ParticleType, material: Material);
+    fn materials(&self) -> &[Material];
+    fn set_materials(&mut self, materials: Vec<Material>) -> Result<(), String>;
+    fn find_material(&self, name: &str) -> Option<ParticleType>;
+
+    // Force fields
+    fn add_force_field(&mut self, field: ForceField);
+    fn clear_force_fields(&mut self);
+    fn set_mutual_gravity(&mut self, gravity: Option<MutualGravity>);
+
+    // World boundaries
+    fn boundaries(&self) -> &Boundaries;
+    fn set_boundaries(&mut self, boundaries: Boundaries);
+
+    // Static colliders
+    fn add_collider(&mut self, collider: Collider);
+    fn clear_colliders(&mut self);
+    fn colliders(&self) -> &[Collider];
+
+    // Control rods (rod = index returned by add_control_rod)
+    fn add_control_rod(&mut self, particle_type: ParticleType, from: (f32, f32), to: (f32, f32), radius: f32) -> usize;
+    fn control_rod(&self, rod: usize) -> &ControlRod;
+    fn set_control_rod_velocity(&mut self, rod: usize, velocity: (f32, f32));
+    fn set_control_rod_travel(&mut self, rod: usize, min_offset: (f32, f32), max_offset: (f32, f32));
+    fn move_control_rod(&mut self, rod: usize, offset: (f32, f32));
+
+    // Broadphase settings
+    fn set_broadphase(&mut self, kind: BroadphaseKind);
+
+    // Threading settings
+    fn set_parallel(&mut self, parallel: bool);
+
+    // Neutron energy (from position - last_position)
+    fn kinetic_energy(&self, i: usize) -> f32;
+    fn energy_group(&self, i: usize) -> EnergyGroup;
+
+    // Energy accounting (yield, power curve, heat)
+    fn stats(&self) -> &Stats;
+    fn reset_stats(&mut self);
+    fn deposit_heat(&mut self, i: usize, energy: f32);
+    fn set_temperature(&mut self, i: usize, temperature: f32);
+
+    // Chain-reaction tree (export with lineage::to_dot or lineage::to_json)
+    fn lineage(&self) -> &Lineage;
+    fn set_lineage_recording(&mut self, recording: bool);
+    fn clear_lineage(&mut self);
+
+    // Coolant (run by advance with the fixed step; samples[i] = coolant at particle i)
+    fn set_coolant(&mut self, coolant: Option<Box<dyn FluidSolver>>);
+    fn coolant(&self) -> Option<&dyn FluidSolver>;
+    fn add_pump(&mut self, pump: Pump);
+    fn clear_pumps(&mut self);
+    fn couple_to_fluid(&mut self, samples: &[FluidSample], dt: f32) -> Vec<FluidSource>;
+
+    // Random draws
+    fn random_momentum(&mut self, magnitude: f32) -> (f32, f32);
 
     // Add particle
-    fn defer_particle_with_momentum(&mut self, position: (f32, f32), mass: f32, momentum: (f32, f32)) -> Particle;
-    fn add_particle_with_momentum(&mut self, position: (f32, f32), mass: f32, momentum: (f32, f32));
-    fn add_fissile(&mut self, position: (f32, f32), mass: f32);
-    fn add_reflector(&mut self, position: (f32, f32), mass: f32);
-    fn add_starter_cap(&mut self, position: (f32, f32), mass: f32);
+    fn defer_particle_with_momentum(&mut self, particle_type: ParticleType, position: (f32, f32), radius: f32, mass: f32, momentum: (f32, f32)) -> Particle;
+    fn add_particle_with_momentum(&mut self, particle_type: ParticleType, position: (f32, f32), radius: f32, mass: f32, momentum: (f32, f32)) -> ParticleId;
+    fn add_particle(&mut self, particle_type: ParticleType, position: (f32, f32), radius: f32, mass: f32) -> ParticleId;
+    fn defer_emissions(&mut self, emissions: &[Emission], position: (f32, f32), parent: Option<ParticleId>, generation: u32) -> Vec<Particle>;
 }
 
 // Create a simulation object
-pub fn create_simulation(grid_res: usize) -> Simulation {
+// (identical seed + identical inputs = identical particle states)
+pub fn create_simulation(seed: u64) -> Simulation {
     return Simulation {
-        particles: vec![],
-        grid_res: grid_res,
-        last_t: unsafe { glfwGetTime() as f64 },
+        particles: create_particles(),
+        broadphase: create_broadphase(BroadphaseKind::SpatialHash),
+        rng: XorShiftRng::seed_from_u64(seed),
+        materials: default_materials(),
+        force_fields: vec![],
+        mutual_gravity: None,
+        gravity_tree: create_barnes_hut(),
+        boundaries: create_default_boundaries(),
+        colliders: vec![],
+        control_rods: vec![],
+        stats: create_stats(),
+        lineage: create_lineage(),
+        coolant: None,
+        pumps: vec![],
+        decay_heat: vec![],
+        fixed_dt: DEFAULT_FIXED_DT,
+        substeps: DEFAULT_SUBSTEPS,
+        max_steps: DEFAULT_MAX_STEPS,
+        accumulator: 0.0,
+        parallel: false,
     }
 }
 
 // Implement simulation
 impl Simulatable for Simulation {
-    fn simulate(&mut self, cull: bool, detonate: bool) {
-        // Step 1: Get dt (in sec)
-        let t = unsafe { glfwGetTime() as f64 };
-        let dt: f32 = self.last_t as f32 - t as f32;
-
-        // Step 1b: Detonate starter caps?
+    fn step(&mut self, dt: f32, detonate: bool) {
+        // Step 1: Detonate (anything with detonation products, e.g. starter caps)?
         if detonate {
             let mut to_add: Vec<Particle> = vec![];
+            let mut to_remove: Vec<ParticleId> = vec![];
             for i in 0..self.particles.len() {
-                if self.particles[i].particle_type == ParticleType::StarterCap && self.particles[i].mass > 0.0 {
+                let products = self.material(self.particles.particle_type(i)).detonation_products.clone();
+                if !products.is_empty() {
+                    let position = self.particles.position(i);
+                    to_add.extend(self.defer_emissions(&products, position, None, 0));
+                    to_remove.push(self.particles.id(i));
+                }
+            }
+            for id in to_remove {
+                self.particles.despawn(id);
+            }
+            for particle in to_add {
+                spawn(self, particle);
+            }
+        }
 
-                    // Random momentum
-                    for _ in 0..10 {
-                        let mx = (rand::random::<f32>() - 0.5) * crate::N_MOMENTUM;
-                        let my = (rand::random::<f32>() - 0.5) * crate::N_MOMENTUM;
-                        to_add.push(self.defer_particle_with_momentum(self.particles[i].position, crate::N_RADIUS, (mx, my)));
-                    }
+        // Step 2: Run as many fixed steps as dt covers
+        self.accumulator += dt;
+        let mut steps = 0;
+        while self.accumulator >= self.fixed_dt {
+            // Too far behind? Drop the backlog (run slower than real time instead)
+            if steps == self.max_steps {
+                self.accumulator = 0.0;
+                break;
+            }
+
+            self.advance();
+            self.accumulator -= self.fixed_dt;
+            steps += 1;
+        }
+    }
+
+    // Advance by one fixed step (in substeps)
+    fn advance(&mut self) {
+        let dt = self.substep_dt();
+
+        for _ in 0..self.substeps {
+            // Step 1: Set control rod velocities
+            self.drive_control_rods();
+
+            // Step 2: Decay unstable particles
+            self.decay();
+
+            // Step 3: Cool hot particles
+            self.cool();
+
+            // Step 4: Apply world boundaries (walls, wrap, escape)
+            self.apply_boundaries();
+
+            // Step 5: Resolve collisions with static colliders
+            self.resolve_static_collisions();
+
+            // Step 6: Resolve collisions
+            let pairs = self.find_pairs();
+            self.resolve_collisions(&pairs);
+
+            // Step 7: Resolve static collisions again (contacts can push particles through a wall)
+            self.resolve_static_collisions();
+
+            // Step 8: Accumulate forces
+            self.accumulate_forces();
+
+            // Step 9: Verlet integrate particles
+            self.integrate(dt);
+
+            // Step 10: Advance the clock
+            self.stats.time += dt as f64;
+        }
 
-                    self.particles[i].mass = 0.0;
-                    self.particles[i].position.0 = -100000.0;
+        // Couple the coolant (once per fixed step, so it runs at the same rate whatever the frame rate)
+        self.couple_coolant();
+
+        // Sample the power and population curves
+        let alive = neutron_census(self);
+        record_sample(&mut self.stats, self.fixed_dt, alive);
+    }
+
+    // Give every control rod member its rod's velocity (Verlet carries them from there)
+    // (slowed to a stop at the ends of the rod's travel)
+    fn drive_control_rods(&mut self) {
+        let dt = self.substep_dt();
+        for rod in &mut self.control_rods {
+            let velocity = rod.velocity;
+            let moved = travel(rod, (velocity.0 * dt, velocity.1 * dt));
+            for &id in &rod.members {
+                if let Some(i) = self.particles.index_of(id) {
+                    set_velocity(&mut self.particles, i, (moved.0 / dt, moved.1 / dt), dt);
                 }
             }
-            self.particles.append(&mut to_add);
         }
+    }
 
-        // DO IN SUBSTEPS
-        let mut last_grid = vec![];
-        for _ in 0..8 {
-            // Step 2: Resolve collisions with floor
-            for i in 0..self.particles.len() {
-                // Get y-component
-                let y = self.particles[i].position.1;
-
-                // Get radius
-                let radius = self.particles[i].mass;
-
-                // If y-component is < radius, resolve
-                if y < radius {
-                    // Collision:
-                    // Move by +/- 1/2 * normal * collision depth
-                    let depth = radius - y;
-                    self.particles[i].position.1 += 0.5 * depth;
+    // Let unstable particles decay (each one independently, by its material's half-life)
+    fn decay(&mut self) {
+        let dt = self.substep_dt();
+        let mut to_add: Vec<Particle> = vec![];
+        let mut to_remove: Vec<ParticleId> = vec![];
+        for i in 0..self.particles.len() {
+            // Stable?
+            let particle_type = self.particles.particle_type(i);
+            let half_life = self.material(particle_type).half_life;
+            if !half_life.is_finite() {
+                continue;
+            }
+
+            // Chance to decay this substep: 1 - 2^(-dt / half-life)
+            if self.rng.gen::<f32>() >= 1.0 - (-dt / half_life).exp2() {
+                continue;
+            }
+
+            // Release the decay products and heat, then transmute (or vanish)
+            let (products, decay_into, energy) = {
+                let material = self.material(particle_type);
+                (material.decay_products.clone(), material.decay_into, material.energy_release.decay)
+            };
+            record(&mut self.stats.decays, &mut self.stats.energy_released, energy);
+            let position = self.particles.position(i);
+
+            // The heat goes into the coolant around it (just tallied without one), not the particle that decayed
+            if self.coolant.is_some() {
+                self.decay_heat.push(FluidSource {
+                    position: position,
+                    radius: self.particles.radius(i),
+                    momentum: (0.0, 0.0),
+                    heat: energy,
+                });
+            }
+            let (parent, generation) = (self.particles.parent(i), self.particles.generation(i));
+            to_add.extend(self.defer_emissions(&products, position, parent, generation));
+            match decay_into {
+                Some(into) => {
+                    // Take on the product's mass and size (at this particle's temperature), keeping its velocity
+                    let (mass, radius, expansion) = {
+                        let material = self.material(into);
+                        (material.mass, material.radius, material.thermal_expansion)
+                    };
+                    let scale = 1.0 + expansion * (self.particles.temperature(i) - AMBIENT_TEMPERATURE);
+                    self.particles.set_particle_type(i, into);
+                    self.particles.set_mass(i, mass);
+                    self.particles.set_radius(i, if scale > 0.0 { radius * scale } else { radius });
+
+                    // Decayed into a neutron? That's a neutron birth
+                    if self.material(into).neutron && !self.material(particle_type).neutron {
+                        record_birth(&mut self.stats, generation);
+                        record_node(&mut self.lineage, self.particles.id(i), parent, generation, self.stats.time as f32);
+                    }
                 }
+                None => to_remove.push(self.particles.id(i)),
             }
+        }
 
-            // Step 3: Resolve collisions
-            let grid = self.construct_grid();
-            self.resolve_collisions(&grid);
+        for id in to_remove {
+            self.particles.despawn(id);
+        }
+        for particle in to_add {
+            spawn(self, particle);
+        }
+    }
 
-            // Step 4: Verlet integrate particles
-            self.integrate(dt / 2.0);
+    // Conduct heat away to the surroundings (excess temperature decays exponentially)
+    fn cool(&mut self) {
+        let dt = self.substep_dt();
+        for i in 0..self.particles.len() {
+            let cooling_rate = self.material(self.particles.particle_type(i)).cooling_rate;
+            let temperature = self.particles.temperature(i);
+            if cooling_rate > 0.0 && temperature != AMBIENT_TEMPERATURE {
+                let excess = (temperature - AMBIENT_TEMPERATURE) * (-cooling_rate * dt).exp();
+                self.set_temperature(i, AMBIENT_TEMPERATURE + excess);
+            }
+        }
+    }
+
+    // Reflect, wrap or despawn particles at the edges of the world
+    fn apply_boundaries(&mut self) {
+        let mut escaped: Vec<ParticleId> = vec![];
+        for i in 0..self.particles.len() {
+            // Immovable (infinite mass) particles stay where they were put
+            if !self.particles.mass(i).is_finite() {
+                continue;
+            }
+
+            let mut position = self.particles.position(i);
+            let mut last_position = self.particles.last_position(i);
+            let mut checked_position = self.particles.checked_position(i);
+            let restitution = self.material(self.particles.particle_type(i)).restitution;
+            if apply_boundaries(&self.boundaries, &mut position, &mut last_position, &mut checked_position, self.particles.radius(i), restitution) {
+                self.particles.set_position(i, position);
+                self.particles.set_last_position(i, last_position);
+                self.particles.set_checked_position(i, checked_position);
+            } else {
+                escaped.push(self.particles.id(i));
+            }
+        }
 
-            last_grid = grid;
+        // Despawn escaped particles (after the loop, so indices don't shift under it)
+        for id in escaped {
+            self.particles.despawn(id);
         }
+    }
 
-        // Step 5: Cull
-        if cull {
-            // Get all in-bounds particles
-            let mut new_particles: Vec<Particle> = vec![];
-            for cell in &last_grid {
-                for i in cell {
-                    new_particles.push(self.particles[*i]);
+    // Push particles out of static colliders and bounce them off
+    // (swept from where the last pass left each particle, so nothing slips through between passes)
+    fn resolve_static_collisions(&mut self) {
+        // Nothing to hit? Everything is clear where it is
+        if self.colliders.is_empty() {
+            for i in 0..self.particles.len() {
+                let position = self.particles.position(i);
+                self.particles.set_checked_position(i, position);
+            }
+            return;
+        }
+
+        for i in 0..self.particles.len() {
+            // Immovable (infinite mass) particles stay where they were put
+            if !self.particles.mass(i).is_finite() {
+                continue;
+            }
+
+            let mut position = self.particles.position(i);
+            let mut last_position = self.particles.last_position(i);
+            let previous = self.particles.checked_position(i);
+            let radius = self.particles.radius(i);
+            let restitution = self.material(self.particles.particle_type(i)).restitution;
+            for _ in 0..STATIC_COLLIDER_PASSES {
+                let mut hit = false;
+                for collider in &self.colliders {
+                    hit |= collide(collider, previous, &mut position, &mut last_position, radius, restitution);
+                }
+                if !hit {
+                    break;
                 }
             }
+            self.particles.set_position(i, position);
+            self.particles.set_last_position(i, last_position);
+            self.particles.set_checked_position(i, position);
+        }
+    }
+
+    // Sum every force field (+ mutual gravitation) into each particle's acceleration
+    fn accumulate_forces(&mut self) {
+        let dt = self.substep_dt();
 
-            // Update
-            self.particles = new_particles;
+        // Rebuild the gravity tree?
+        if self.mutual_gravity.is_some() {
+            self.gravity_tree.build(self.particles.positions(), self.particles.masses());
         }
+
+        let accelerations: Vec<(f32, f32)> = {
+            let fields = &self.force_fields;
+            let particles = &self.particles;
+            let mutual_gravity = &self.mutual_gravity;
+            let gravity_tree = &self.gravity_tree;
+            let sample = |i: usize| {
+                let mass = particles.mass(i);
+                let position = particles.position(i);
+                let mut accel = total_acceleration(fields, &ForceSample {
+                    particle_type: particles.particle_type(i),
+                    position: position,
+                    velocity: velocity(particles, i, dt),
+                    mass: mass,
+                });
+
+                // Immovable (infinite mass) particles are left out of mutual gravitation
+                if let Some(ref gravity) = *mutual_gravity {
+                    if mass.is_finite() {
+                        let a = gravity_tree.acceleration(i, position, particles.positions(), particles.masses(), gravity);
+                        accel = (accel.0 + a.0, accel.1 + a.1);
+                    }
+                }
+                accel
+            };
+            if self.parallel {
+                (0..particles.len()).into_par_iter().map(sample).collect()
+            } else {
+                (0..particles.len()).map(sample).collect()
+            }
+        };
+        self.particles.accelerations_mut().copy_from_slice(&accelerations);
     }
 
     fn integrate(&mut self, dt: f32) {
-        for i in 0..self.particles.len() {
-            // Get x_n, x_(n-1), a
-            let pres = self.particles[i].position;
-            let past = self.particles[i].last_position;
-            let accel = self.particles[i].acceleration;
+        let (position, last_position, acceleration) = self.particles.kinematics_mut();
+        if self.parallel {
+            position.par_iter_mut().zip(last_position.par_iter_mut()).zip(acceleration.par_iter())
+                .for_each(|((pres, past), accel)| integrate_particle(pres, past, *accel, dt));
+        } else {
+            position.iter_mut().zip(last_position.iter_mut()).zip(acceleration.iter())
+                .for_each(|((pres, past), accel)| integrate_particle(pres, past, *accel, dt));
+        }
+    }
+
+    // Find candidate collision pairs (i < j) with the broadphase
+    fn find_pairs(&mut self) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+
+        // Step 1: Any particles near a periodic seam? (copies of them go across it)
+        let max_radius = self.particles.radii().iter().cloned().fold(0.0, f32::max);
+        let images = periodic_images(&self.boundaries, self.particles.positions(), 2.0 * max_radius);
+        if images.is_empty() {
+            self.broadphase.find_pairs(self.particles.positions(), self.particles.radii(), &mut pairs);
+            return pairs;
+        }
+
+        // Step 2: Search the particles + their images
+        let n = self.particles.len();
+        let mut positions = self.particles.positions().to_vec();
+        let mut radii = self.particles.radii().to_vec();
+        for &(position, i) in &images {
+            let radius = radii[i];
+            positions.push(position);
+            radii.push(radius);
+        }
+        let mut found = vec![];
+        self.broadphase.find_pairs(&positions, &radii, &mut found);
 
-            // Get next x, next y
-            let xnext = 2.0 * pres.0 - past.0 + accel.0 * dt*dt;
-            let ynext = 2.0 * pres.1 - past.1 + accel.1 * dt*dt;
+        // Step 3: Map images back to the particles they copy (a pair can be found more than once)
+        let original = |k: usize| if k < n { k } else { images[k - n].1 };
+        for (a, b) in found {
+            let (i, j) = (original(a), original(b));
+            if i != j {
+                pairs.push((i.min(j), i.max(j)));
+            }
+        }
+        pairs.sort();
+        pairs.dedup();
+        return pairs;
+    }
+
+    fn resolve_collisions(&mut self, pairs: &Vec<(usize, usize)>) {
+        // Phase 1: Find contacts (read-only, so safe to split across threads)
+        let contacts: Vec<Contact> = if self.parallel {
+            let (particles, boundaries) = (&self.particles, &self.boundaries);
+            pairs.par_iter().filter_map(|&pair| find_contact(particles, boundaries, pair)).collect()
+        } else {
+            pairs.iter().filter_map(|&pair| find_contact(&self.particles, &self.boundaries, pair)).collect()
+        };
+
+        // Phase 2: Apply them in pair order (this is where random draws happen)
+        let mut to_add: Vec<Particle> = vec![];
+        let mut to_remove: Vec<ParticleId> = vec![];
+        let mut absorbed: HashSet<ParticleId> = HashSet::new();    // Neutrons already taken by a reaction
+        let dt = self.substep_dt();
+        for contact in &contacts {
+            // Incoming velocities (reactions depend on how fast a neutron hit, not how it bounced)
+            let vi = velocity(&self.particles, contact.i, dt);
+            let vj = velocity(&self.particles, contact.j, dt);
+            apply_contact(self, contact);
+            react_to_hit(self, contact.i, contact.j, vj, &mut to_add, &mut to_remove, &mut absorbed);
+            react_to_hit(self, contact.j, contact.i, vi, &mut to_add, &mut to_remove, &mut absorbed);
+        }
+
+        // Despawn spent particles (indices only shift now, after every contact is applied)
+        for id in to_remove {
+            self.particles.despawn(id);
+        }
 
-            // Update position/last_position
-            self.particles[i].last_position = self.particles[i].position;
-            self.particles[i].position = (xnext, ynext);
+        // Spawn the to_add particles
+        for particle in to_add {
+            spawn(self, particle);
         }
     }
 
-    // Construct a fixed-grid index of all particles
-    fn construct_grid(&self) -> Vec<Vec<usize>> {
-        // Step 1: Allocate grid
-        let mut grid: Vec<Vec<usize>> = vec![vec![]; self.grid_res * self.grid_res];
+    fn set_timestep(&mut self, fixed_dt: f32, substeps: usize) {
+        let old_dt = self.substep_dt();
+        self.fixed_dt = fixed_dt;
+        self.substeps = substeps.max(1);
+        let new_dt = self.substep_dt();
 
-        // Step 2: Assign particles to their cells
+        // Velocity lives in (position - last_position) / dt, so rescale it to the new dt
         for i in 0..self.particles.len() {
-            let pos = self.particles[i].position;
+            let pres = self.particles.position(i);
+            let past = self.particles.last_position(i);
+            let vx = (pres.0 - past.0) / old_dt;
+            let vy = (pres.1 - past.1) / old_dt;
+            self.particles.set_last_position(i, (pres.0 - vx * new_dt, pres.1 - vy * new_dt));
+        }
+    }
 
-            // Step 1: Is particle even in bounds?
-            if pos.0 < 0.0 || pos.0 >= crate::SCR_WIDTH as f32 || pos.1 < 0.0 || pos.1 >= crate::SCR_HEIGHT as f32 {
-                continue;
+    fn set_max_steps(&mut self, max_steps: usize) {
+        // At least one step per call, or the sim never moves
+        self.max_steps = max_steps.max(1);
+    }
+
+    // Length of one substep (in sec)
+    fn substep_dt(&self) -> f32 {
+        return self.fixed_dt / self.substeps as f32;
+    }
+
+    fn material(&self, particle_type: ParticleType) -> &Material {
+        return &self.materials[particle_type.0];
+    }
+
+    fn set_material(&mut self, particle_type: ParticleType, material: Material) {
+        self.materials[particle_type.0] = material;
+    }
+
+    fn materials(&self) -> &[Material] {
+        return &self.materials;
+    }
+
+    // Swap the whole registry (existing particles keep their indices)
+    // Refused if a live particle's type would fall off the end of it
+    fn set_materials(&mut self, materials: Vec<Material>) -> Result<(), String> {
+        if let Some(&particle_type) = self.particles.particle_types().iter().find(|t| t.0 >= materials.len()) {
+            return Err(format!("registry has {} materials, but live particles use type {} (\"{}\")",
+                materials.len(), particle_type.0, self.materials[particle_type.0].name));
+        }
+        self.materials = materials;
+        return Ok(());
+    }
+
+    fn find_material(&self, name: &str) -> Option<ParticleType> {
+        return find_material(&self.materials, name);
+    }
+
+    fn add_force_field(&mut self, field: ForceField) {
+        self.force_fields.push(field);
+    }
+
+    fn clear_force_fields(&mut self) {
+        self.force_fields.clear();
+    }
+
+    fn set_mutual_gravity(&mut self, gravity: Option<MutualGravity>) {
+        self.mutual_gravity = gravity;
+    }
+
+    fn boundaries(&self) -> &Boundaries {
+        return &self.boundaries;
+    }
+
+    fn set_boundaries(&mut self, boundaries: Boundaries) {
+        self.boundaries = boundaries;
+    }
+
+    fn add_collider(&mut self, collider: Collider) {
+        self.colliders.push(collider);
+    }
+
+    fn clear_colliders(&mut self) {
+        self.colliders.clear();
+    }
+
+    fn colliders(&self) -> &[Collider] {
+        return &self.colliders;
+    }
+
+    // Add a control rod: a line of immovable particles (e.g. absorbers) from one point to another
+    fn add_control_rod(&mut self, particle_type: ParticleType, from: (f32, f32), to: (f32, f32), radius: f32) -> usize {
+        let members = rod_positions(from, to, radius)
+            .into_iter()
+            .map(|position| self.add_particle(particle_type, position, radius, ::std::f32::INFINITY))
+            .collect();
+        self.control_rods.push(create_control_rod(members));
+        return self.control_rods.len() - 1;
+    }
+
+    fn control_rod(&self, rod: usize) -> &ControlRod {
+        return &self.control_rods[rod];
+    }
+
+    // Drive a rod at a velocity (in units/sec) until told otherwise
+    fn set_control_rod_velocity(&mut self, rod: usize, velocity: (f32, f32)) {
+        self.control_rods[rod].velocity = velocity;
+    }
+
+    // Limit how far a rod can travel from where it was added (offsets per axis, e.g. (0, -depth) to (0, 0))
+    fn set_control_rod_travel(&mut self, rod: usize, min_offset: (f32, f32), max_offset: (f32, f32)) {
+        self.control_rods[rod].min_offset = min_offset;
+        self.control_rods[rod].max_offset = max_offset;
+    }
+
+    // Teleport a rod by an offset (without giving it any velocity; stops at the ends of its travel)
+    fn move_control_rod(&mut self, rod: usize, offset: (f32, f32)) {
+        let offset = travel(&mut self.control_rods[rod], offset);
+        for &id in &self.control_rods[rod].members {
+            if let Some(i) = self.particles.index_of(id) {
+                let pres = self.particles.position(i);
+                let past = self.particles.last_position(i);
+                self.particles.set_position(i, (pres.0 + offset.0, pres.1 + offset.1));
+                self.particles.set_last_position(i, (past.0 + offset.0, past.1 + offset.1));
             }
+        }
+    }
+
+    // Swap the broadphase backend (takes effect on the next substep)
+    fn set_broadphase(&mut self, kind: BroadphaseKind) {
+        self.broadphase = create_broadphase(kind);
+        self.broadphase.set_parallel(self.parallel);
+    }
 
-            // Step 2: Calculate grid coordinates
-            let cx = (pos.0 / (self.grid_res * crate::SCR_WIDTH as usize) as f32) as usize;
-            let cy = (pos.1 / (self.grid_res * crate::SCR_HEIGHT as usize) as f32) as usize;
+    fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+        self.broadphase.set_parallel(parallel);
+    }
+
+    fn kinetic_energy(&self, i: usize) -> f32 {
+        return kinetic_energy(self.particles.mass(i), velocity(&self.particles, i, self.substep_dt()));
+    }
 
-            // Step 3: Insert into proper cell
-            grid[cx+cy*self.grid_res].push(i);
+    fn energy_group(&self, i: usize) -> EnergyGroup {
+        return energy_group(self.kinetic_energy(i), self.particles.mass(i));
+    }
+
+    fn stats(&self) -> &Stats {
+        return &self.stats;
+    }
+
+    // Start counting from zero (e.g. after setting up a scene)
+    // (neutrons already flying count as born now, so generations stay balanced)
+    fn reset_stats(&mut self) {
+        let alive = neutron_census(self);
+        self.stats = create_stats();
+        self.stats.births = alive.clone();
+        self.stats.alive = alive;
+    }
+
+    fn lineage(&self) -> &Lineage {
+        return &self.lineage;
+    }
+
+    // Start (or stop) adding neutron births to the tree (off by default)
+    fn set_lineage_recording(&mut self, recording: bool) {
+        self.lineage.recording = recording;
+    }
+
+    fn clear_lineage(&mut self) {
+        clear_lineage(&mut self.lineage);
+    }
+
+    // Heat a particle: temperature rises by energy / (heat capacity * mass)
+    fn deposit_heat(&mut self, i: usize, energy: f32) {
+        let capacity = thermal_capacity(self, i);
+        if capacity > 0.0 {
+            let temperature = self.particles.temperature(i) + energy / capacity;
+            self.set_temperature(i, temperature);
         }
+    }
 
-        return grid;
+    // Change a particle's temperature, expanding (or contracting) it to match
+    // (radius = radius at ambient * (1 + thermal expansion * (temperature - ambient)))
+    fn set_temperature(&mut self, i: usize, temperature: f32) {
+        let expansion = self.material(self.particles.particle_type(i)).thermal_expansion;
+        if expansion != 0.0 {
+            let old_scale = 1.0 + expansion * (self.particles.temperature(i) - AMBIENT_TEMPERATURE);
+            let new_scale = 1.0 + expansion * (temperature - AMBIENT_TEMPERATURE);
+            if old_scale > 0.0 && new_scale > 0.0 {
+                let radius = self.particles.radius(i) * new_scale / old_scale;
+                self.particles.set_radius(i, radius);
+            }
+        }
+        self.particles.set_temperature(i, temperature);
     }
 
-    fn resolve_collisions(&mut self, grid: &Vec<Vec<usize>>) {
-        let mut to_add: Vec<Particle> = vec![];
+    // Set the coolant the particles are coupled to (None = no coolant)
+    fn set_coolant(&mut self, coolant: Option<Box<dyn FluidSolver>>) {
+        self.coolant = coolant;
+        self.decay_heat.clear();
+    }
 
-        for cx in 0..self.grid_res {
-            for cy in 0..self.grid_res {
-                // Get the particles in this cell
-                let cell_particles = &grid[cx+cy*self.grid_res];
-
-                // Loop O(n^2) over this cell
-                for i in 0..cell_particles.len() {
-                    for j in (i+1)..cell_particles.len() {
-
-                        // Get i&j cell
-                        let ci = cell_particles[i];
-                        let cj = cell_particles[j];
-
-                        // Ignore neutron-less collisions
-                        if self.particles[ci].particle_type != ParticleType::Neutron && self.particles[cj].particle_type != ParticleType::Neutron {
-                            continue;
-                        }
-
-                        // Contains non-neutron?
-                        let contains_non_neutron = self.particles[ci].particle_type != ParticleType::Neutron || self.particles[cj].particle_type != ParticleType::Neutron;
-
-                        // Compare radii
-                        let ri = self.particles[ci].mass;
-                        let rj = self.particles[cj].mass;
-        
-                        // Compare positions
-                        let pi = self.particles[ci].position;
-                        let pj = self.particles[cj].position;
-                        let dx = pi.0 - pj.0;
-                        let dy = pi.1 - pj.1;
-                        let distance = (dx*dx + dy*dy).sqrt();
-        
-                        // If collision
-                        if distance < ri + rj {
-                            // Collision:
-                            // Move by +/- 1/2 * normal * collision depth
-                            let depth = (ri + rj) - distance;
-                            let nx = dx / distance;
-                            let ny = dy / distance;
-                            
-                            if self.particles[ci].particle_type == ParticleType::Neutron {
-                                self.particles[ci].position.0 += (if contains_non_neutron { 1.0 } else { 0.5 }) * depth * nx;
-                                self.particles[ci].position.1 += (if contains_non_neutron { 1.0 } else { 0.5 }) * depth * ny;
-                            } else if self.particles[ci].particle_type == ParticleType::Fissile {
-                                // Diminish mass
-                                self.particles[ci].mass -= 1.0;
-
-                                // Spawn a neutron (25% chance)
-                                if rand::random::<f32>() > 0.75 {
-                                    let ipx = (self.particles[cj].position.0 - self.particles[cj].last_position.0) * crate::N_RADIUS;
-                                    let ipy = (self.particles[cj].position.1 - self.particles[cj].last_position.1) * crate::N_RADIUS;
-                                    to_add.push(self.defer_particle_with_momentum(
-                                        self.particles[cj].position, 
-                                        crate::N_RADIUS, 
-                                        (ipx, ipy)
-                                    ));
-                                }
-
-                                // If mass is < 0, teleport OOB
-                                if self.particles[ci].mass < 0.0 {
-                                    self.particles[ci].position.0 -= 100000.0;
-                                }
-                            } else {
-                                // Diminish mass
-                                self.particles[ci].mass -= 0.1;
-
-                                // If mass is < 0, teleport OOB
-                                if self.particles[ci].mass < 0.0 {
-                                    self.particles[ci].position.0 -= 100000.0;
-                                }
-                            }
-
-                            if self.particles[cj].particle_type == ParticleType::Neutron {
-                                self.particles[cj].position.0 -= (if contains_non_neutron { 1.0 } else { 0.5 }) * depth * nx;
-                                self.particles[cj].position.1 -= (if contains_non_neutron { 1.0 } else { 0.5 }) * depth * ny;
-                            } else if self.particles[ci].particle_type == ParticleType::Fissile {
-                                // Diminish mass
-                                self.particles[cj].mass -= 1.0;
-
-                                // Spawn a neutron (25% chance)
-                                if rand::random::<f32>() > 0.75 {
-                                    let ipx = (self.particles[ci].position.0 - self.particles[ci].last_position.0) * crate::N_RADIUS;
-                                    let ipy = (self.particles[ci].position.1 - self.particles[ci].last_position.1) * crate::N_RADIUS;
-                                    to_add.push(self.defer_particle_with_momentum(
-                                        self.particles[ci].position, 
-                                        crate::N_RADIUS, 
-                                        (ipx, ipy)
-                                    ));
-                                }
-
-                                // If mass is < 0, teleport OOB
-                                if self.particles[cj].mass < 0.0 {
-                                    self.particles[cj].position.0 -= 100000.0;
-                                }
-                            } else {
-                                // Diminish mass
-                                self.particles[cj].mass -= 0.1;
-
-                                // If mass is < 0, teleport OOB
-                                if self.particles[cj].mass < 0.0 {
-                                    self.particles[cj].position.0 -= 100000.0;
-                                }
-                            }
-                        }
-                    }
-                }
+    // Coolant (for drawing)
+    fn coolant(&self) -> Option<&dyn FluidSolver> {
+        return self.coolant.as_ref().map(|coolant| coolant.as_ref());
+    }
+
+    // Add a pump pushing the coolant
+    fn add_pump(&mut self, pump: Pump) {
+        self.pumps.push(pump);
+    }
+
+    // Remove every pump
+    fn clear_pumps(&mut self) {
+        self.pumps.clear();
+    }
+
+    // Run one fixed step of the coolant, coupled to the particles
+    fn couple_coolant(&mut self) {
+        // Step 1: No coolant? Nothing to do (taken out while coupling, since coupling borrows the sim)
+        let mut coolant = match self.coolant.take() {
+            Some(coolant) => coolant,
+            None => return,
+        };
+        let dt = self.fixed_dt;
+
+        // Step 2: Drag and heat exchange, both ways (plus the decay heat released since the last step)
+        let samples = coolant.sample(self.particles.positions());
+        let mut sources = self.couple_to_fluid(&samples, dt);
+        sources.extend(self.decay_heat.drain(..));
+        coolant.inject(&sources);
+
+        // Step 3: Pumps (their own pass)
+        for pump in &self.pumps {
+            coolant.pump(pump, dt);
+        }
+
+        // Step 4: Advect and project the coolant
+        coolant.simulate(dt);
+        self.coolant = Some(coolant);
+    }
+
+    // Drag particles along with the coolant and trade heat with it over dt sec
+    // Returns what the coolant gets in exchange (momentum and heat are conserved)
+    fn couple_to_fluid(&mut self, samples: &[FluidSample], dt: f32) -> Vec<FluidSource> {
+        let substep_dt = self.substep_dt();
+        let mut sources = vec![];
+        for i in 0..self.particles.len().min(samples.len()) {
+            let (drag_coefficient, heat_transfer) = {
+                let material = self.material(self.particles.particle_type(i));
+                (material.drag, material.heat_transfer)
+            };
+            if drag_coefficient <= 0.0 && heat_transfer <= 0.0 {
+                continue;
+            }
+            let sample = samples[i];
+            let mut source = FluidSource {
+                position: self.particles.position(i),
+                radius: self.particles.radius(i),
+                momentum: (0.0, 0.0),
+                heat: 0.0,
+            };
+
+            // Step 1: Drag (immovable particles neither feel it nor push back)
+            let mass = self.particles.mass(i);
+            if drag_coefficient > 0.0 && mass.is_finite() {
+                let v = velocity(&self.particles, i, substep_dt);
+                let dragged = drag(v, sample.velocity, drag_coefficient, mass, dt);
+                set_velocity(&mut self.particles, i, dragged, substep_dt);
+                source.momentum = (mass * (v.0 - dragged.0), mass * (v.1 - dragged.1));
             }
+
+            // Step 2: Heat exchange
+            let capacity = thermal_capacity(self, i);
+            if heat_transfer > 0.0 && capacity > 0.0 && capacity.is_finite() {
+                let temperature = self.particles.temperature(i);
+                let exchanged = exchange_heat(temperature, sample.temperature, heat_transfer, capacity, dt);
+                self.set_temperature(i, exchanged);
+                source.heat = capacity * (temperature - exchanged);
+            }
+
+            sources.push(source);
         }
+        return sources;
+    }
 
-        // Spawn the to_add particles
-        self.particles.append(&mut to_add);
+    // Random momentum, uniform in [-magnitude/2, magnitude/2) on each axis
+    fn random_momentum(&mut self, magnitude: f32) -> (f32, f32) {
+        let mx = (self.rng.gen::<f32>() - 0.5) * magnitude;
+        let my = (self.rng.gen::<f32>() - 0.5) * magnitude;
+        return (mx, my);
     }
 
-    fn defer_particle_with_momentum(&mut self, position: (f32, f32), mass: f32, momentum: (f32, f32)) -> Particle {
-        // p = mv -> v = p/m
+    fn defer_particle_with_momentum(&mut self, particle_type: ParticleType, position: (f32, f32), radius: f32, mass: f32, momentum: (f32, f32)) -> Particle {
+        // p = mv -> v = p/m (in units/sec)
         let vx = momentum.0 / mass;
         let vy = momentum.1 / mass;
+        let dt = self.substep_dt();
 
         // Create particle
         Particle {
             position: position,
-            last_position: (position.0 - vx, position.1 - vy),
+            last_position: (position.0 - vx * dt, position.1 - vy * dt),
             acceleration: (0.0, 0.0),
+            radius: radius,
             mass: mass,
-            particle_type: ParticleType::Neutron,
+            inventory: 1.0,
+            temperature: AMBIENT_TEMPERATURE,
+            generation: 0,
+            parent: None,
+            birth_time: 0.0,
+            particle_type: particle_type,
         }
     }
 
-    fn add_particle_with_momentum(&mut self, position: (f32, f32), mass: f32, momentum: (f32, f32)) {
-        // p = mv -> v = p/m
-        let vx = momentum.0 / mass;
-        let vy = momentum.1 / mass;
-
+    fn add_particle_with_momentum(&mut self, particle_type: ParticleType, position: (f32, f32), radius: f32, mass: f32, momentum: (f32, f32)) -> ParticleId {
         // Create particle
-        let particle = Particle {
-            position: position,
-            last_position: (position.0 - vx, position.1 - vy),
-            acceleration: (0.0, 0.0),
-            mass: mass,
-            particle_type: ParticleType::Neutron,
-        };
+        let particle = self.defer_particle_with_momentum(particle_type, position, radius, mass, momentum);
 
         // Insert into particles list
-        self.particles.push(particle);
+        return spawn(self, particle);
     }
 
-    fn add_fissile(&mut self, position: (f32, f32), mass: f32) {
+    // Add a particle at rest
+    fn add_particle(&mut self, particle_type: ParticleType, position: (f32, f32), radius: f32, mass: f32) -> ParticleId {
         // Create particle
         let particle = Particle {
             position: position,
             last_position: position,
             acceleration: (0.0, 0.0),
+            radius: radius,
             mass: mass,
-            particle_type: ParticleType::Fissile,
+            inventory: 1.0,
+            temperature: AMBIENT_TEMPERATURE,
+            generation: 0,
+            parent: None,
+            birth_time: 0.0,
+            particle_type: particle_type,
         };
 
         // Insert into particles list
-        self.particles.push(particle);
+        return spawn(self, particle);
     }
 
-    fn add_reflector(&mut self, position: (f32, f32), mass: f32) {
-        // Create particle
-        let particle = Particle {
-            position: position,
-            last_position: position,
-            acceleration: (0.0, 0.0),
-            mass: mass,
-            particle_type: ParticleType::Reflector,
+    // Sample reaction products at a position (using each product material's default radius and mass)
+    fn defer_emissions(&mut self, emissions: &[Emission], position: (f32, f32), parent: Option<ParticleId>, generation: u32) -> Vec<Particle> {
+        let mut particles = vec![];
+        for emission in emissions {
+            let (radius, mass) = {
+                let material = self.material(emission.particle_type);
+                (material.radius, material.mass)
+            };
+            let u = self.rng.gen::<f32>();
+            for _ in 0..sample_multiplicity(&emission.multiplicity, u) {
+                let momentum = self.random_momentum(emission.momentum);
+                let mut particle = self.defer_particle_with_momentum(emission.particle_type, position, radius, mass, momentum);
+                particle.generation = generation;
+                particle.parent = parent;
+                particles.push(particle);
+            }
+        }
+        return particles;
+    }
+}
+
+// Helper function to insert a particle, stamping its birth time
+// (neutron births are counted per generation and added to the lineage tree, if it's recording)
+fn spawn(sim: &mut Simulation, mut particle: Particle) -> ParticleId {
+    particle.birth_time = sim.stats.time as f32;
+    let id = sim.particles.push(particle);
+    if sim.material(particle.particle_type).neutron {
+        record_birth(&mut sim.stats, particle.generation);
+        record_node(&mut sim.lineage, id, particle.parent, particle.generation, particle.birth_time);
+    }
+    return id;
+}
+
+// Helper function to get a particle's heat capacity (heat per degree)
+// (pinned particles have infinite mass, so they warm by their material's mass instead;
+// if that is infinite too they soak up heat without warming)
+fn thermal_capacity(sim: &Simulation, i: usize) -> f32 {
+    let material = sim.material(sim.particles.particle_type(i));
+    let mass = if sim.particles.mass(i).is_finite() { sim.particles.mass(i) } else { material.mass };
+    return material.heat_capacity * mass;
+}
+
+// Helper function to count live neutrons per generation
+fn neutron_census(sim: &Simulation) -> Vec<u64> {
+    let mut alive: Vec<u64> = vec![];
+    for i in 0..sim.particles.len() {
+        if sim.material(sim.particles.particle_type(i)).neutron {
+            let generation = sim.particles.generation(i) as usize;
+            if alive.len() <= generation {
+                alive.resize(generation + 1, 0);
+            }
+            alive[generation] += 1;
+        }
+    }
+    return alive;
+}
+
+// Helper function to create the default boundaries (a floor, open everywhere else)
+fn create_default_boundaries() -> Boundaries {
+    let mut boundaries = create_boundaries((0.0, 0.0), DEFAULT_WORLD_SIZE, Boundary::Open);
+    boundaries.bottom = Boundary::Wall;
+    return boundaries;
+}
+
+// Helper function to Verlet integrate one particle
+fn integrate_particle(position: &mut (f32, f32), last_position: &mut (f32, f32), accel: (f32, f32), dt: f32) {
+    // Get x_n, x_(n-1)
+    let pres = *position;
+    let past = *last_position;
+
+    // Get next x, next y
+    let xnext = 2.0 * pres.0 - past.0 + accel.0 * dt*dt;
+    let ynext = 2.0 * pres.1 - past.1 + accel.1 * dt*dt;
+
+    // Update position/last_position
+    *last_position = pres;
+    *position = (xnext, ynext);
+}
+
+// Helper function to test a candidate pair for contact (across periodic seams too)
+fn find_contact(particles: &Particles, boundaries: &Boundaries, pair: (usize, usize)) -> Option<Contact> {
+    let (ci, cj) = pair;
+
+    // Compare radii
+    let radii = particles.radii();
+    let ri = radii[ci];
+    let rj = radii[cj];
+
+    // Compare positions
+    let positions = particles.positions();
+    let (dx, dy) = separation(boundaries, positions[ci], positions[cj]);
+    let distance = (dx*dx + dy*dy).sqrt();
+
+    // No collision?
+    if distance >= ri + rj || distance <= 0.0 {
+        return None;
+    }
+
+    return Some(Contact {
+        i: ci,
+        j: cj,
+        depth: (ri + rj) - distance,
+        normal: (dx / distance, dy / distance),
+    });
+}
+
+// Helper function to get a particle's velocity (in units/sec)
+fn velocity(particles: &Particles, i: usize, dt: f32) -> (f32, f32) {
+    let pres = particles.position(i);
+    let past = particles.last_position(i);
+    return ((pres.0 - past.0) / dt, (pres.1 - past.1) / dt);
+}
+
+// Helper function to set a particle's velocity (in units/sec)
+fn set_velocity(particles: &mut Particles, i: usize, velocity: (f32, f32), dt: f32) {
+    let pres = particles.position(i);
+    particles.set_last_position(i, (pres.0 - velocity.0 * dt, pres.1 - velocity.1 * dt));
+}
+
+// Helper function to separate a contact and exchange momentum
+// Both steps are weighted by inverse mass, so the center of mass stays put and
+// total momentum is conserved (infinite mass = immovable)
+fn apply_contact(sim: &mut Simulation, contact: &Contact) {
+    let (i, j, n) = (contact.i, contact.j, contact.normal);
+    let dt = sim.substep_dt();
+
+    // Step 1: Inverse masses
+    let wi = 1.0 / sim.particles.mass(i);
+    let wj = 1.0 / sim.particles.mass(j);
+    let w = wi + wj;
+    if !(w > 0.0) {
+        return;
+    }
+
+    // Step 2: Push apart (moving last_position too, so this adds no velocity)
+    let vi = velocity(&sim.particles, i, dt);
+    let vj = velocity(&sim.particles, j, dt);
+    let pi = sim.particles.position(i);
+    let pj = sim.particles.position(j);
+    let si = contact.depth * wi / w;
+    let sj = contact.depth * wj / w;
+    sim.particles.set_position(i, (pi.0 + si * n.0, pi.1 + si * n.1));
+    sim.particles.set_position(j, (pj.0 - sj * n.0, pj.1 - sj * n.1));
+
+    // Step 3: Exchange momentum along the normal (only if approaching)
+    let vn = (vi.0 - vj.0) * n.0 + (vi.1 - vj.1) * n.1;
+    let (mut vi, mut vj) = (vi, vj);
+    if vn < 0.0 {
+        let e = combined_restitution(sim.material(sim.particles.particle_type(i)), sim.material(sim.particles.particle_type(j)));
+        let impulse = -(1.0 + e) * vn / w;
+        vi = (vi.0 + impulse * wi * n.0, vi.1 + impulse * wi * n.1);
+        vj = (vj.0 - impulse * wj * n.0, vj.1 - impulse * wj * n.1);
+    }
+    set_velocity(&mut sim.particles, i, vi, dt);
+    set_velocity(&mut sim.particles, j, vj, dt);
+}
+
+// Helper function to react to a neutron hit (particle i, hit by other, which came in at incoming velocity)
+fn react_to_hit(sim: &mut Simulation, i: usize, other: usize, incoming: (f32, f32), to_add: &mut Vec<Particle>, to_remove: &mut Vec<ParticleId>, absorbed: &mut HashSet<ParticleId>) {
+    // Only non-neutrons hit by a neutron react
+    let particle_type = sim.particles.particle_type(i);
+    if sim.material(particle_type).neutron || !sim.material(sim.particles.particle_type(other)).neutron {
+        return;
+    }
+    let dt = sim.substep_dt();
+
+    // Already absorbed by something else this substep?
+    let neutron = sim.particles.id(other);
+    if absorbed.contains(&neutron) {
+        return;
+    }
+
+    // Step 1: Sample the reaction from the cross sections at the incoming energy
+    // (hot fuel fissions less: Doppler feedback)
+    let energy = kinetic_energy(sim.particles.mass(other), incoming);
+    let fission_scale = doppler_factor(sim.material(particle_type).doppler, AMBIENT_TEMPERATURE, sim.particles.temperature(i));
+    let u = sim.rng.gen::<f32>();
+    let reaction = sample_reaction(&sim.material(particle_type).cross_sections, energy, fission_scale, u);
+
+    // Step 2: Deplete inventory (e.g. fuel burns up, reflectors wear down)
+    let inventory = sim.particles.inventory(i) - wear(sim.material(particle_type), reaction);
+    sim.particles.set_inventory(i, inventory);
+
+    // Step 3: React
+    match reaction {
+        Reaction::Scatter => {
+            // Slow the neutron down (moderating materials)
+            let energy_loss = sim.material(particle_type).energy_loss;
+            if energy_loss > 0.0 {
+                let v = moderate(velocity(&sim.particles, other, dt), energy_loss);
+                set_velocity(&mut sim.particles, other, v, dt);
+            }
+        }
+        Reaction::Capture => {
+            to_remove.push(neutron);
+            absorbed.insert(neutron);
+            let energy = sim.material(particle_type).energy_release.capture;
+            record(&mut sim.stats.captures, &mut sim.stats.energy_released, energy);
+            sim.deposit_heat(i, energy);
+        }
+        Reaction::Fission => {
+            // Absorb the neutron and release the fission products and heat
+            // (fission neutrons are born fast, whatever speed the incoming one had)
+            to_remove.push(neutron);
+            absorbed.insert(neutron);
+            let energy = sim.material(particle_type).energy_release.fission;
+            record(&mut sim.stats.fissions, &mut sim.stats.energy_released, energy);
+            sim.deposit_heat(i, energy);
+            let position = sim.particles.position(other);
+            let products = sim.material(particle_type).fission_products.clone();
+            let generation = sim.particles.generation(other) + 1;
+            to_add.extend(sim.defer_emissions(&products, position, Some(neutron), generation));
+        }
+    }
+
+    // Used up? Despawn once this substep's contacts are done
+    // (a second hit this substep pushes the same handle again, which despawn ignores)
+    if inventory < 0.0 {
+        to_remove.push(sim.particles.id(i));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simulation::forces::Region;
+    use simulation::cpu_fluid::create_cpu_fluid;
+
+    // Helper function to look up a built-in material
+    fn material_named(sim: &Simulation, name: &str) -> ParticleType {
+        return sim.find_material(name).unwrap();
+    }
+
+    // Helper function to fill a walled box with fuel and fast neutrons
+    fn reactor_scene(seed: u64, parallel: bool) -> Simulation {
+        let mut sim = create_simulation(seed);
+        sim.set_boundaries(create_boundaries((0.0, 0.0), (400.0, 400.0), Boundary::Wall));
+        sim.set_parallel(parallel);
+        let (fuel, neutron) = (material_named(&sim, "fissile"), material_named(&sim, "neutron"));
+        for k in 0..100 {
+            let position = (20.0 + (k % 10) as f32 * 40.0, 20.0 + (k / 10) as f32 * 40.0);
+            let (radius, mass) = (sim.material(fuel).radius, sim.material(fuel).mass);
+            sim.add_particle(fuel, position, radius, mass);
+        }
+        for k in 0..400 {
+            let position = (5.0 + (k % 20) as f32 * 19.5, 5.0 + (k / 20) as f32 * 19.5);
+            let (radius, mass) = (sim.material(neutron).radius, sim.material(neutron).mass);
+            let momentum = sim.random_momentum(12000.0);
+            sim.add_particle_with_momentum(neutron, position, radius, mass, momentum);
+        }
+        return sim;
+    }
+
+    // Helper function to sum every particle's momentum
+    fn total_momentum(sim: &Simulation) -> (f64, f64) {
+        let dt = sim.substep_dt();
+        let mut total = (0.0, 0.0);
+        for i in 0..sim.particles.len() {
+            let (mass, v) = (sim.particles.mass(i) as f64, velocity(&sim.particles, i, dt));
+            total = (total.0 + mass * v.0 as f64, total.1 + mass * v.1 as f64);
+        }
+        return total;
+    }
+
+    #[test]
+    fn max_steps_of_zero_still_steps() {
+        let mut sim = create_simulation(0);
+        let neutron = material_named(&sim, "neutron");
+        let (radius, mass) = (sim.material(neutron).radius, sim.material(neutron).mass);
+        sim.add_particle_with_momentum(neutron, (100.0, 500.0), radius, mass, (600.0, 0.0));
+        sim.set_max_steps(0);
+
+        // A long frame runs one step and drops the rest of the backlog
+        sim.step(1.0, false);
+        assert!((sim.particles.position(0).0 - 110.0).abs() < 0.01, "{:?}", sim.particles.position(0));
+    }
+
+    #[test]
+    fn force_fields_accelerate_particles() {
+        // A uniform field over the left half only
+        let mut sim = create_simulation(0);
+        sim.set_boundaries(create_boundaries((-1.0e6, -1.0e6), (1.0e6, 1.0e6), Boundary::Open));
+        let region = Region { min: (0.0, -1000.0), max: (500.0, 1000.0) };
+        sim.add_force_field(ForceField::Gravity { acceleration: (0.0, -100.0), region: Some(region) });
+        let neutron = material_named(&sim, "neutron");
+        let (radius, mass) = (sim.material(neutron).radius, sim.material(neutron).mass);
+        sim.add_particle(neutron, (100.0, 0.0), radius, mass);
+        sim.add_particle(neutron, (800.0, 0.0), radius, mass);
+
+        // One second: y = -g t^2 / 2 inside (to within Verlet's one-substep lag), untouched outside
+        for _ in 0..60 {
+            sim.advance();
+        }
+        assert!((sim.particles.position(0).1 + 50.0).abs() < 0.25, "{:?}", sim.particles.position(0));
+        assert!((velocity(&sim.particles, 0, sim.substep_dt()).1 + 100.0).abs() < 0.5);
+        assert_eq!(sim.particles.position(1), (800.0, 0.0));
+    }
+
+    #[test]
+    fn moderator_thermalizes_fast_neutrons() {
+        // A walled box of fixed moderator blocks with fast neutrons bouncing between them
+        let mut sim = create_simulation(2);
+        sim.set_boundaries(create_boundaries((0.0, 0.0), (400.0, 400.0), Boundary::Wall));
+        let (moderator, neutron) = (material_named(&sim, "moderator"), material_named(&sim, "neutron"));
+        for k in 0..36 {
+            let position = (33.0 + (k % 6) as f32 * 67.0, 33.0 + (k / 6) as f32 * 67.0);
+            let radius = sim.material(moderator).radius;
+            sim.add_particle(moderator, position, radius, ::std::f32::INFINITY);
+        }
+        for k in 0..100 {
+            let position = (5.0 + (k % 10) as f32 * 40.0, 66.0 + (k / 10) as f32 * 33.0);
+            let (radius, mass) = (sim.material(neutron).radius, sim.material(neutron).mass);
+            let momentum = sim.random_momentum(12000.0);
+            sim.add_particle_with_momentum(neutron, position, radius, mass, momentum);
+        }
+
+        // Count the neutrons in each group
+        let groups = |sim: &Simulation| {
+            let dt = sim.substep_dt();
+            let mut counts = (0, 0);
+            for i in 0..sim.particles.len() {
+                if sim.particles.particle_type(i) == neutron {
+                    let energy = kinetic_energy(sim.particles.mass(i), velocity(&sim.particles, i, dt));
+                    match energy_group(energy, sim.particles.mass(i)) {
+                        EnergyGroup::Thermal => counts.0 += 1,
+                        _ => counts.1 += 1,
+                    }
+                }
+            }
+            counts
         };
+        let (thermal, _) = groups(&sim);
+        assert!(thermal < 10, "{} thermal at the start", thermal);
+        for _ in 0..300 {
+            sim.advance();
+        }
+        let (thermal, faster) = groups(&sim);
+        assert!(thermal > 9 * faster, "{} thermal, {} faster", thermal, faster);
+    }
 
-        // Insert into particles list
-        self.particles.push(particle);
+    #[test]
+    fn heated_fuel_expands_and_cools_back_down() {
+        let mut sim = create_simulation(0);
+        let fuel = material_named(&sim, "fissile");
+        let material = sim.material(fuel).clone();
+        sim.add_particle(fuel, (500.0, 500.0), material.radius, material.mass);
+
+        // One fission's heat: dT = E / (c m), and the radius grows with it
+        sim.deposit_heat(0, material.energy_release.fission);
+        let rise = material.energy_release.fission / (material.heat_capacity * material.mass);
+        assert!((sim.particles.temperature(0) - AMBIENT_TEMPERATURE - rise).abs() < 1e-3);
+        assert!((sim.particles.radius(0) - material.radius * (1.0 + material.thermal_expansion * rise)).abs() < 1e-4);
+
+        // A second later the excess has decayed by exp(-cooling rate), and the radius shrunk to match
+        for _ in 0..60 {
+            sim.advance();
+        }
+        let excess = sim.particles.temperature(0) - AMBIENT_TEMPERATURE;
+        assert!((excess - rise * (-material.cooling_rate).exp()).abs() < 1e-2, "{} != {}", excess, rise * (-material.cooling_rate).exp());
+        assert!((sim.particles.radius(0) - material.radius * (1.0 + material.thermal_expansion * excess)).abs() < 1e-4);
+
+        // Long after, back at ambient (to within f32 steps at ambient: tiny excesses stop decaying)
+        for _ in 0..3000 {
+            sim.advance();
+        }
+        assert!((sim.particles.temperature(0) - AMBIENT_TEMPERATURE).abs() < 0.05, "{}", sim.particles.temperature(0));
+        assert!((sim.particles.radius(0) - material.radius).abs() < 1e-4);
     }
 
-    fn add_starter_cap(&mut self, position: (f32, f32), mass: f32) {
-        // Create particle
-        let particle = Particle {
-            position: position,
-            last_position: position,
-            acceleration: (0.0, 0.0),
-            mass: mass,
-            particle_type: ParticleType::StarterCap,
+    #[test]
+    fn hot_fuel_fissions_less() {
+        // The same neutrons at cold and hot fuel (twice ambient halves the fission cross section)
+        let fissions = |temperature: f32| {
+            let mut sim = reactor_scene(5, false);
+            for i in 0..sim.particles.len() {
+                if !sim.material(sim.particles.particle_type(i)).neutron {
+                    sim.particles.set_temperature(i, temperature);
+                }
+            }
+            for _ in 0..3 {
+                sim.advance();
+            }
+            sim.stats().fissions.count
         };
+        let (cold, hot) = (fissions(AMBIENT_TEMPERATURE), fissions(4.0 * AMBIENT_TEMPERATURE));
+        assert!(cold > 50 && (hot as f32) < 0.6 * cold as f32, "cold {} hot {}", cold, hot);
+    }
 
-        // Insert into particles list
-        self.particles.push(particle);
+    #[test]
+    fn coolant_runs_once_per_fixed_step() {
+        // Hot fuel in a pumped coolant, stepped at two frame rates
+        let scene = || {
+            let mut sim = create_simulation(0);
+            sim.set_boundaries(create_boundaries((0.0, 0.0), (400.0, 400.0), Boundary::Wall));
+            sim.set_coolant(Some(Box::new(create_cpu_fluid((400.0, 400.0), (40, 40)))));
+            sim.add_pump(Pump { position: (200.0, 100.0), radius: 40.0, force: (0.0, 1.0e4) });
+            let fuel = material_named(&sim, "fissile");
+            let (radius, mass) = (sim.material(fuel).radius, sim.material(fuel).mass);
+            for k in 0..4 {
+                sim.add_particle(fuel, (140.0 + k as f32 * 40.0, 200.0), radius, mass);
+                sim.set_temperature(k, 2.0 * AMBIENT_TEMPERATURE);
+            }
+            sim
+        };
+        let probes: Vec<(f32, f32)> = (0..100).map(|k| (20.0 + (k % 10) as f32 * 40.0, 20.0 + (k / 10) as f32 * 40.0)).collect();
+
+        // Half a fixed step doesn't touch the coolant; the other half runs one step of it
+        let mut sim = scene();
+        sim.step(0.5 * sim.fixed_dt, false);
+        assert_eq!(sim.coolant().unwrap().sample(&[(200.0, 100.0)])[0].velocity, (0.0, 0.0));
+        sim.step(0.5 * sim.fixed_dt, false);
+        assert!(sim.coolant().unwrap().sample(&[(200.0, 100.0)])[0].velocity.1 > 0.0);
+
+        // Same coolant and particles at 60 and 120 frames per sec
+        let (mut slow, mut fast) = (scene(), scene());
+        for _ in 0..60 {
+            slow.step(1.0 / 60.0, false);
+        }
+        for _ in 0..120 {
+            fast.step(1.0 / 120.0, false);
+        }
+        for (a, b) in slow.coolant().unwrap().sample(&probes).iter().zip(fast.coolant().unwrap().sample(&probes).iter()) {
+            assert_eq!((a.velocity, a.temperature), (b.velocity, b.temperature));
+        }
+        for i in 0..4 {
+            assert_eq!(slow.particles.position(i), fast.particles.position(i));
+            assert_eq!(slow.particles.temperature(i), fast.particles.temperature(i));
+        }
+        assert!(slow.particles.temperature(0) < 2.0 * AMBIENT_TEMPERATURE);
+    }
+
+    #[test]
+    fn contacts_conserve_momentum() {
+        // Closed scene: no neutrons (so no reactions), no force fields, nothing reaches the edges
+        let mut sim = create_simulation(3);
+        sim.set_boundaries(create_boundaries((-1.0e6, -1.0e6), (1.0e6, 1.0e6), Boundary::Open));
+        let types = [material_named(&sim, "fissile"), material_named(&sim, "reflector"), material_named(&sim, "moderator")];
+        for k in 0..200 {
+            let particle_type = types[k % types.len()];
+            let (radius, mass) = (sim.material(particle_type).radius, sim.material(particle_type).mass);
+            let position = ((k % 20) as f32 * 30.0, (k / 20) as f32 * 30.0);
+            let momentum = sim.random_momentum(200.0 * mass);
+            sim.add_particle_with_momentum(particle_type, position, radius, mass, momentum);
+        }
+
+        let before = total_momentum(&sim);
+        let mut contacts = 0;
+        for _ in 0..30 {
+            contacts += sim.find_pairs().len();
+            sim.advance();
+        }
+        let after = total_momentum(&sim);
+
+        // Only f32 rounding may change it (relative to the momentum moving around)
+        let scale: f64 = (0..sim.particles.len())
+            .map(|i| sim.particles.mass(i) as f64 * 100.0)
+            .sum();
+        assert!(contacts > 0);
+        assert_eq!(sim.particles.len(), 200);
+        assert!((after.0 - before.0).abs() < 1.0e-4 * scale, "{:?} -> {:?}", before, after);
+        assert!((after.1 - before.1).abs() < 1.0e-4 * scale, "{:?} -> {:?}", before, after);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn periodic_seam_has_contacts() {
+        // Two equal elastic particles either side of the x seam, one heading across it at the other
+        let mut sim = create_simulation(0);
+        sim.set_boundaries(create_boundaries((0.0, 0.0), (200.0, 200.0), Boundary::Periodic));
+        let reflector = material_named(&sim, "reflector");
+        let (radius, mass) = (sim.material(reflector).radius, sim.material(reflector).mass);
+        sim.add_particle_with_momentum(reflector, (180.0, 100.0), radius, mass, (300.0 * mass, 0.0));
+        sim.add_particle(reflector, (20.0, 100.0), radius, mass);
+
+        // Head-on elastic hit between equal masses: they swap velocities
+        // (at the seam, so the first one stops before it gets there)
+        for _ in 0..20 {
+            sim.advance();
+        }
+        let dt = sim.substep_dt();
+        let (va, vb) = (velocity(&sim.particles, 0, dt), velocity(&sim.particles, 1, dt));
+        assert!(va.0.abs() < 1.0, "{:?}", va);
+        assert!((vb.0 - 300.0).abs() < 1.0, "{:?}", vb);
+        assert!(va.1.abs() < 1.0e-3 && vb.1.abs() < 1.0e-3);
+        assert!(sim.particles.position(0).0 > 170.0, "{:?}", sim.particles.position(0));
+
+        // Overlapping across the corner of both seams: still a pair
+        sim.particles.set_position(0, (2.0, 197.0));
+        sim.particles.set_position(1, (198.0, 3.0));
+        assert_eq!(sim.find_pairs(), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn static_colliders_contain_particles() {
+        // A 200 x 200 vessel full of fast neutrons and a few heavier reflectors to push them around
+        let mut sim = create_simulation(11);
+        sim.set_boundaries(create_boundaries((-1.0e6, -1.0e6), (1.0e6, 1.0e6), Boundary::Open));
+        sim.add_collider(Collider::Polygon { points: vec![(100.0, 100.0), (300.0, 100.0), (300.0, 300.0), (100.0, 300.0)] });
+        let (neutron, reflector) = (material_named(&sim, "neutron"), material_named(&sim, "reflector"));
+        for k in 0..200 {
+            let position = (110.0 + (k % 15) as f32 * 12.5, 110.0 + (k / 15) as f32 * 12.5);
+            let (radius, mass) = (sim.material(neutron).radius, sim.material(neutron).mass);
+            let momentum = sim.random_momentum(12000.0);
+            sim.add_particle_with_momentum(neutron, position, radius, mass, momentum);
+        }
+        for k in 0..4 {
+            let position = (150.0 + (k % 2) as f32 * 100.0, 150.0 + (k / 2) as f32 * 100.0);
+            let (radius, mass) = (sim.material(reflector).radius, sim.material(reflector).mass);
+            let momentum = sim.random_momentum(500.0 * mass);
+            sim.add_particle_with_momentum(reflector, position, radius, mass, momentum);
+        }
+
+        // Spawned against a wall, heading away from it (its back-extrapolated last position is outside)
+        let (radius, mass) = (sim.material(neutron).radius, sim.material(neutron).mass);
+        sim.add_particle_with_momentum(neutron, (105.0, 200.0), radius, mass, (12000.0, 0.0));
+
+        // A small post in the middle, and a neutron fired straight at it (12.5 units per substep, more than the post's width)
+        sim.add_collider(Collider::Circle { center: (200.0, 280.0), radius: 5.0 });
+        let fast = sim.add_particle_with_momentum(neutron, (150.0, 280.0), radius, mass, (6000.0, 0.0));
+        sim.advance();
+        let fast = sim.particles.position(sim.particles.index_of(fast).unwrap());
+        assert!(fast.0 < 200.0, "fast neutron passed through the post to {:?}", fast);
+
+        // (checked positions: integration can carry a particle over a wall until the next substep catches it)
+        for step in 0..600 {
+            sim.advance();
+            for i in 0..sim.particles.len() {
+                let position = sim.particles.checked_position(i);
+                assert!(position.0 > 100.0 && position.0 < 300.0 && position.1 > 100.0 && position.1 < 300.0,
+                    "particle {} outside at {:?} after step {}", i, position, step);
+                let (dx, dy) = (position.0 - 200.0, position.1 - 280.0);
+                assert!(dx*dx + dy*dy > 8.9 * 8.9, "particle {} inside the post at {:?} after step {}", i, position, step);
+            }
+        }
+    }
+
+    #[test]
+    fn control_rods_stop_at_travel_limits() {
+        let mut sim = create_simulation(0);
+        let absorber = material_named(&sim, "absorber");
+        let rod = sim.add_control_rod(absorber, (100.0, 500.0), (100.0, 600.0), 8.0);
+        sim.set_control_rod_travel(rod, (0.0, -300.0), (0.0, 200.0));
+        let tip = |sim: &Simulation| sim.particles.position(sim.particles.index_of(sim.control_rod(rod).members[0]).unwrap());
+
+        // Driven down for far longer than it takes to get there
+        sim.set_control_rod_velocity(rod, (0.0, -200.0));
+        for _ in 0..300 {
+            sim.advance();
+        }
+        assert!((tip(&sim).1 - 200.0).abs() < 0.01, "{:?}", tip(&sim));
+        assert_eq!(velocity(&sim.particles, 0, sim.substep_dt()), (0.0, 0.0));
+
+        // Then up, and teleported past the top
+        sim.set_control_rod_velocity(rod, (0.0, 200.0));
+        for _ in 0..300 {
+            sim.advance();
+        }
+        sim.move_control_rod(rod, (0.0, 1000.0));
+        assert!((tip(&sim).1 - 700.0).abs() < 0.01, "{:?}", tip(&sim));
+        assert_eq!(tip(&sim).0, 100.0);
+    }
+
+    #[test]
+    fn set_materials_keeps_live_types_in_range() {
+        let mut sim = create_simulation(0);
+        let last = ParticleType(sim.materials().len() - 1);
+        let (radius, mass) = (sim.material(last).radius, sim.material(last).mass);
+        sim.add_particle(last, (0.0, 100.0), radius, mass);
+
+        // A shorter registry would strand that particle, so it's refused (and the old one kept)
+        let mut shorter = default_materials();
+        shorter.pop();
+        assert!(sim.set_materials(shorter).is_err());
+        assert_eq!(sim.material(last).name, default_materials().last().unwrap().name);
+
+        // Once nothing uses the missing type, it's fine
+        sim.particles.retain(|_| false);
+        let mut shorter = default_materials();
+        shorter.pop();
+        assert!(sim.set_materials(shorter).is_ok());
+    }
+
+    #[test]
+    fn reset_stats_with_live_neutrons() {
+        let mut sim = create_simulation(0);
+        let neutron = material_named(&sim, "neutron");
+        let (radius, mass) = (sim.material(neutron).radius, sim.material(neutron).mass);
+        for k in 0..10 {
+            sim.add_particle_with_momentum(neutron, (100.0 + 20.0 * k as f32, 500.0), radius, mass, (0.0, 100.0));
+        }
+        sim.advance();
+
+        // Reset mid-run, then one more neutron: the ones already flying count as born
+        sim.reset_stats();
+        sim.add_particle_with_momentum(neutron, (50.0, 500.0), radius, mass, (0.0, 100.0));
+        sim.advance();
+        assert_eq!(sim.stats().births, vec![11]);
+        assert_eq!(sim.stats().alive, vec![11]);
+        assert_eq!(sim.stats().k_effective, None);
+    }
+
+    #[test]
+    fn decay_into_a_neutron_is_a_birth() {
+        let mut sim = create_simulation(0);
+        let (neutron, precursor) = (material_named(&sim, "neutron"), material_named(&sim, "precursor"));
+        let mut unstable = sim.material(precursor).clone();
+        unstable.half_life = 1.0e-6;
+        unstable.decay_into = Some(neutron);
+        unstable.decay_products = vec![];
+        sim.set_material(precursor, unstable);
+        let (radius, mass) = (sim.material(precursor).radius, sim.material(precursor).mass);
+        sim.add_particle(precursor, (100.0, 500.0), radius, mass);
+
+        sim.advance();
+        assert_eq!(sim.stats().decays.count, 1);
+        assert_eq!(sim.stats().births, vec![1]);
+        assert_eq!(sim.stats().alive, vec![1]);
+    }
+
+    #[test]
+    fn decay_products_take_their_materials_mass_and_radius() {
+        let mut sim = create_simulation(0);
+        let (neutron, precursor) = (material_named(&sim, "neutron"), material_named(&sim, "precursor"));
+        let mut unstable = sim.material(precursor).clone();
+        unstable.half_life = 1.0e-6;
+        unstable.decay_into = Some(neutron);
+        unstable.decay_products = vec![];
+        unstable.mass = 90.0;
+        unstable.radius = 12.0;
+        sim.set_material(precursor, unstable);
+        sim.add_particle_with_momentum(precursor, (100.0, 500.0), 12.0, 90.0, (9000.0, 0.0));
+
+        sim.advance();
+        assert_eq!(sim.particles.particle_type(0), neutron);
+        assert_eq!(sim.particles.mass(0), sim.material(neutron).mass);
+        assert_eq!(sim.particles.radius(0), sim.material(neutron).radius);
+        assert!((velocity(&sim.particles, 0, sim.substep_dt()).0 - 100.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn decay_heat_goes_to_the_coolant() {
+        // A precursor that decays straight into fuel (which could hold the heat, but shouldn't get it)
+        let scene = |coolant: bool| {
+            let mut sim = create_simulation(0);
+            let (fuel, precursor) = (material_named(&sim, "fissile"), material_named(&sim, "precursor"));
+            let mut unstable = sim.material(precursor).clone();
+            unstable.half_life = 1.0e-6;
+            unstable.decay_into = Some(fuel);
+            unstable.decay_products = vec![];
+            unstable.energy_release.decay = 1.0e6;
+            sim.set_material(precursor, unstable);
+            if coolant {
+                sim.set_coolant(Some(Box::new(create_cpu_fluid((1920.0, 1080.0), (48, 27)))));
+            }
+            let (radius, mass) = (sim.material(precursor).radius, sim.material(precursor).mass);
+            sim.add_particle(precursor, (500.0, 500.0), radius, mass);
+            sim.advance();
+            sim
+        };
+
+        // No coolant: tallied, and the fuel stays at ambient
+        let sim = scene(false);
+        assert_eq!(sim.stats().decays.count, 1);
+        assert_eq!(sim.stats().energy_released, 1.0e6);
+        assert_eq!(sim.particles.temperature(0), AMBIENT_TEMPERATURE);
+
+        // Coolant: tallied, and the coolant around it warms up instead
+        let sim = scene(true);
+        assert_eq!(sim.stats().energy_released, 1.0e6);
+        assert!(sim.coolant().unwrap().sample(&[(500.0, 500.0)])[0].temperature > AMBIENT_TEMPERATURE);
+        assert!(sim.particles.temperature(0) < AMBIENT_TEMPERATURE + 1.0);
+    }
+
+    #[test]
+    fn lineage_records_only_when_asked() {
+        let mut sim = create_simulation(0);
+        let neutron = material_named(&sim, "neutron");
+        let (radius, mass) = (sim.material(neutron).radius, sim.material(neutron).mass);
+        sim.add_particle(neutron, (100.0, 500.0), radius, mass);
+        assert!(sim.lineage().nodes.is_empty());
+
+        sim.set_lineage_recording(true);
+        sim.add_particle(neutron, (200.0, 500.0), radius, mass);
+        sim.add_particle(neutron, (300.0, 500.0), radius, mass);
+        assert_eq!(sim.lineage().nodes.len(), 2);
+        assert_eq!(sim.stats().births, vec![3]);
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let mut serial = reactor_scene(7, false);
+        let mut parallel = reactor_scene(7, true);
+        for _ in 0..60 {
+            serial.advance();
+            parallel.advance();
+        }
+
+        assert!(serial.stats().fissions.count > 0);
+        assert_eq!(serial.particles.len(), parallel.particles.len());
+        for i in 0..serial.particles.len() {
+            assert_eq!(serial.particles.particle_type(i), parallel.particles.particle_type(i));
+            assert_eq!(serial.particles.position(i), parallel.particles.position(i));
+            assert_eq!(serial.particles.last_position(i), parallel.particles.last_position(i));
+        }
+    }
+}
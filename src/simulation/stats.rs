@@ -0,0 +1,128 @@
+// Finished neutrons the k estimate pools (newest generations first) before it trusts the ratio
+const K_MIN_FINISHED: u64 = 100;
+
+// Running total of one kind of event
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Tally {
+    pub count: u64,
+    pub energy: f64,    // Energy released by these events (simulation units)
+}
+
+// One point of the time series (recorded after every physics step)
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    pub time: f64,              // In sec
+    pub power: f32,             // Energy released per sec over the step
+    pub energy_released: f64,   // Cumulative
+    pub neutrons: usize,        // Neutron population
+    pub k_effective: Option<f32>,   // Estimate at the time (see Stats::k_effective)
+}
+
+// Energy accounting for a run
+#[derive(Clone, Debug)]
+pub struct Stats {
+    pub time: f64,              // Simulated time (in sec, advanced every substep)
+    pub fissions: Tally,
+    pub captures: Tally,
+    pub decays: Tally,
+    pub energy_released: f64,   // Yield so far (sum of every tally)
+    pub power: f32,             // Energy released per sec over the last physics step
+    pub history: Vec<Sample>,   // Power and population curves, one sample per physics step
+
+    // Neutron generations (0 = source neutrons, n + 1 = born from a fission caused by generation n)
+    pub births: Vec<u64>,       // Neutrons born in each generation
+    pub alive: Vec<u64>,        // Neutrons of each generation still flying (as of the last physics step)
+    pub k_generations: (usize, usize),  // Generations (first, last) the k estimate pools
+    pub k_effective: Option<f32>,   // Children per finished neutron over those generations (see generation_k)
+}
+
+// Create empty stats
+pub fn create_stats() -> Stats {
+    return Stats {
+        time: 0.0,
+        fissions: Tally::default(),
+        captures: Tally::default(),
+        decays: Tally::default(),
+        energy_released: 0.0,
+        power: 0.0,
+        history: vec![],
+        births: vec![],
+        alive: vec![],
+        k_generations: (0, 0),
+        k_effective: None,
+    }
+}
+
+// Count one event and the energy it released
+pub fn record(tally: &mut Tally, energy_released: &mut f64, energy: f32) {
+    tally.count += 1;
+    tally.energy += energy as f64;
+    *energy_released += energy as f64;
+}
+
+// Count a neutron born into a generation
+pub fn record_birth(stats: &mut Stats, generation: u32) {
+    let generation = generation as usize;
+    if stats.births.len() <= generation {
+        stats.births.resize(generation + 1, 0);
+    }
+    stats.births[generation] += 1;
+}
+
+// Multiplication factor of a generation: births in the next one per neutron of this one that is done
+// (a neutron only has children once it's gone, so stragglers still flying don't skew it;
+// delayed neutrons count once their precursor decays)
+pub fn generation_k(stats: &Stats, generation: usize) -> Option<f32> {
+    let (children, finished) = generation_counts(stats, generation);
+    if finished == 0 {
+        return None;
+    }
+    return Some(children as f32 / finished as f32);
+}
+
+// Close a physics step of dt sec: update k and sample the curves
+// (alive = live neutrons per generation)
+pub fn record_sample(stats: &mut Stats, dt: f32, alive: Vec<u64>) {
+    // Step 1: Estimate k, pooling the newest generations until enough of their neutrons are done
+    // (one generation at the leading edge is just a handful of neutrons)
+    stats.alive = alive;
+    let mut children = 0;
+    let mut finished = 0;
+    let mut last = None;
+    for generation in (0..stats.births.len()).rev() {
+        let (c, f) = generation_counts(stats, generation);
+        if f == 0 {
+            continue;
+        }
+        children += c;
+        finished += f;
+        let last = *last.get_or_insert(generation);
+        stats.k_generations = (generation, last);
+        if finished >= K_MIN_FINISHED {
+            break;
+        }
+    }
+    if finished > 0 {
+        stats.k_effective = Some(children as f32 / finished as f32);
+    }
+
+    // Step 2: Sample
+    let previous = stats.history.last().map(|sample| sample.energy_released).unwrap_or(0.0);
+    stats.power = ((stats.energy_released - previous) / dt as f64) as f32;
+    stats.history.push(Sample {
+        time: stats.time,
+        power: stats.power,
+        energy_released: stats.energy_released,
+        neutrons: stats.alive.iter().sum::<u64>() as usize,
+        k_effective: stats.k_effective,
+    });
+}
+
+// Helper function to count a generation's children so far and its finished neutrons
+// (saturating, in case neutrons turn up that were never counted as born)
+fn generation_counts(stats: &Stats, generation: usize) -> (u64, u64) {
+    let born = stats.births.get(generation).cloned().unwrap_or(0);
+    let finished = born.saturating_sub(stats.alive.get(generation).cloned().unwrap_or(0));
+    let children = stats.births.get(generation + 1).cloned().unwrap_or(0);
+    return (children, finished);
+}
@@ -0,0 +1,382 @@
+use simulation::particles::{Particle, ParticleType};
+
+// Stable handle to a particle
+// Dense indices change whenever something is removed (swap-remove), but a handle
+// keeps pointing at the same particle until it is despawned. After that its slot
+// is reused with a bumped generation, so stale handles never alias a new particle.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ParticleId {
+    pub slot: u32,
+    pub generation: u32,
+}
+
+// Particle storage (struct-of-arrays)
+// Each attribute lives in its own contiguous buffer, so hot loops
+// (integration, broadphase, narrowphase) stream only what they touch
+pub struct Particles {
+    position: Vec<(f32, f32)>,
+    last_position: Vec<(f32, f32)>,
+    acceleration: Vec<(f32, f32)>,
+    radius: Vec<f32>,
+    mass: Vec<f32>,
+    inventory: Vec<f32>,
+    temperature: Vec<f32>,
+    generation: Vec<u32>,
+    parent: Vec<Option<ParticleId>>,
+    birth_time: Vec<f32>,
+    particle_type: Vec<ParticleType>,
+    checked_position: Vec<(f32, f32)>,  // Where the static collider pass last left it (see checked_position)
+    slot: Vec<u32>,             // Handle slot of each particle
+
+    // Handle slots
+    slot_generation: Vec<u32>,  // Current generation of each slot
+    slot_index: Vec<usize>,     // Dense index of each live slot
+    free_slots: Vec<u32>,       // Slots free for reuse
+}
+
+pub trait ParticleStorage {
+    // Size
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool;
+
+    // Per-particle access
+    fn get(&self, i: usize) -> Particle;
+    fn position(&self, i: usize) -> (f32, f32);
+    fn last_position(&self, i: usize) -> (f32, f32);
+    fn acceleration(&self, i: usize) -> (f32, f32);
+    fn radius(&self, i: usize) -> f32;
+    fn mass(&self, i: usize) -> f32;
+    fn inventory(&self, i: usize) -> f32;
+    fn temperature(&self, i: usize) -> f32;
+    fn generation(&self, i: usize) -> u32;
+    fn parent(&self, i: usize) -> Option<ParticleId>;
+    fn birth_time(&self, i: usize) -> f32;
+    fn particle_type(&self, i: usize) -> ParticleType;
+    fn checked_position(&self, i: usize) -> (f32, f32);
+    fn set_position(&mut self, i: usize, position: (f32, f32));
+    fn set_last_position(&mut self, i: usize, last_position: (f32, f32));
+    fn set_acceleration(&mut self, i: usize, acceleration: (f32, f32));
+    fn set_radius(&mut self, i: usize, radius: f32);
+    fn set_mass(&mut self, i: usize, mass: f32);
+    fn set_inventory(&mut self, i: usize, inventory: f32);
+    fn set_temperature(&mut self, i: usize, temperature: f32);
+    fn set_particle_type(&mut self, i: usize, particle_type: ParticleType);
+    fn set_checked_position(&mut self, i: usize, checked_position: (f32, f32));
+
+    // Whole-buffer access
+    fn positions(&self) -> &[(f32, f32)];
+    fn accelerations_mut(&mut self) -> &mut [(f32, f32)];
+    fn radii(&self) -> &[f32];
+    fn masses(&self) -> &[f32];
+    fn particle_types(&self) -> &[ParticleType];
+    fn kinematics_mut(&mut self) -> (&mut [(f32, f32)], &mut [(f32, f32)], &[(f32, f32)]);
+
+    // Handles
+    fn id(&self, i: usize) -> ParticleId;
+    fn index_of(&self, id: ParticleId) -> Option<usize>;
+    fn contains(&self, id: ParticleId) -> bool;
+
+    // Add/remove
+    fn push(&mut self, particle: Particle) -> ParticleId;
+    fn despawn(&mut self, id: ParticleId) -> bool;
+    fn retain<F: FnMut(&Particle) -> bool>(&mut self, keep: F);
+}
+
+// Create empty particle storage
+pub fn create_particles() -> Particles {
+    return Particles {
+        position: vec![],
+        last_position: vec![],
+        acceleration: vec![],
+        radius: vec![],
+        mass: vec![],
+        inventory: vec![],
+        temperature: vec![],
+        generation: vec![],
+        parent: vec![],
+        birth_time: vec![],
+        particle_type: vec![],
+        checked_position: vec![],
+        slot: vec![],
+        slot_generation: vec![],
+        slot_index: vec![],
+        free_slots: vec![],
+    }
+}
+
+impl ParticleStorage for Particles {
+    fn len(&self) -> usize {
+        return self.position.len();
+    }
+
+    fn is_empty(&self) -> bool {
+        return self.position.is_empty();
+    }
+
+    // Gather one particle from every buffer
+    fn get(&self, i: usize) -> Particle {
+        return Particle {
+            position: self.position[i],
+            last_position: self.last_position[i],
+            acceleration: self.acceleration[i],
+            radius: self.radius[i],
+            mass: self.mass[i],
+            inventory: self.inventory[i],
+            temperature: self.temperature[i],
+            generation: self.generation[i],
+            parent: self.parent[i],
+            birth_time: self.birth_time[i],
+            particle_type: self.particle_type[i],
+        }
+    }
+
+    fn position(&self, i: usize) -> (f32, f32) {
+        return self.position[i];
+    }
+
+    fn last_position(&self, i: usize) -> (f32, f32) {
+        return self.last_position[i];
+    }
+
+    fn acceleration(&self, i: usize) -> (f32, f32) {
+        return self.acceleration[i];
+    }
+
+    fn radius(&self, i: usize) -> f32 {
+        return self.radius[i];
+    }
+
+    fn mass(&self, i: usize) -> f32 {
+        return self.mass[i];
+    }
+
+    fn inventory(&self, i: usize) -> f32 {
+        return self.inventory[i];
+    }
+
+    fn temperature(&self, i: usize) -> f32 {
+        return self.temperature[i];
+    }
+
+    fn generation(&self, i: usize) -> u32 {
+        return self.generation[i];
+    }
+
+    fn parent(&self, i: usize) -> Option<ParticleId> {
+        return self.parent[i];
+    }
+
+    fn birth_time(&self, i: usize) -> f32 {
+        return self.birth_time[i];
+    }
+
+    fn particle_type(&self, i: usize) -> ParticleType {
+        return self.particle_type[i];
+    }
+
+    // Last position known to be on the right side of every static collider
+    // (where the swept collider test starts; the spawn position until the first pass,
+    // since last_position is back-extrapolated from the velocity and can be anywhere)
+    fn checked_position(&self, i: usize) -> (f32, f32) {
+        return self.checked_position[i];
+    }
+
+    fn set_position(&mut self, i: usize, position: (f32, f32)) {
+        self.position[i] = position;
+    }
+
+    fn set_last_position(&mut self, i: usize, last_position: (f32, f32)) {
+        self.last_position[i] = last_position;
+    }
+
+    fn set_acceleration(&mut self, i: usize, acceleration: (f32, f32)) {
+        self.acceleration[i] = acceleration;
+    }
+
+    fn set_radius(&mut self, i: usize, radius: f32) {
+        self.radius[i] = radius;
+    }
+
+    fn set_mass(&mut self, i: usize, mass: f32) {
+        self.mass[i] = mass;
+    }
+
+    fn set_inventory(&mut self, i: usize, inventory: f32) {
+        self.inventory[i] = inventory;
+    }
+
+    fn set_temperature(&mut self, i: usize, temperature: f32) {
+        self.temperature[i] = temperature;
+    }
+
+    fn set_particle_type(&mut self, i: usize, particle_type: ParticleType) {
+        self.particle_type[i] = particle_type;
+    }
+
+    fn set_checked_position(&mut self, i: usize, checked_position: (f32, f32)) {
+        self.checked_position[i] = checked_position;
+    }
+
+    fn positions(&self) -> &[(f32, f32)] {
+        return &self.position;
+    }
+
+    fn accelerations_mut(&mut self) -> &mut [(f32, f32)] {
+        return &mut self.acceleration;
+    }
+
+    fn radii(&self) -> &[f32] {
+        return &self.radius;
+    }
+
+    fn masses(&self) -> &[f32] {
+        return &self.mass;
+    }
+
+    fn particle_types(&self) -> &[ParticleType] {
+        return &self.particle_type;
+    }
+
+    // Position (mut), last position (mut) and acceleration, for the integrator
+    fn kinematics_mut(&mut self) -> (&mut [(f32, f32)], &mut [(f32, f32)], &[(f32, f32)]) {
+        return (&mut self.position, &mut self.last_position, &self.acceleration);
+    }
+
+    fn id(&self, i: usize) -> ParticleId {
+        let slot = self.slot[i];
+        return ParticleId {
+            slot: slot,
+            generation: self.slot_generation[slot as usize],
+        }
+    }
+
+    // Dense index of a particle (None if it was despawned)
+    fn index_of(&self, id: ParticleId) -> Option<usize> {
+        let slot = id.slot as usize;
+        if slot >= self.slot_generation.len() || self.slot_generation[slot] != id.generation {
+            return None;
+        }
+        return Some(self.slot_index[slot]);
+    }
+
+    fn contains(&self, id: ParticleId) -> bool {
+        return self.index_of(id).is_some();
+    }
+
+    // Scatter one particle into every buffer
+    fn push(&mut self, particle: Particle) -> ParticleId {
+        // Step 1: Take a free slot (or a new one)
+        let slot = match self.free_slots.pop() {
+            Some(slot) => slot,
+            None => {
+                self.slot_generation.push(0);
+                self.slot_index.push(0);
+                (self.slot_generation.len() - 1) as u32
+            }
+        };
+        self.slot_index[slot as usize] = self.len();
+
+        // Step 2: Append to every buffer
+        self.position.push(particle.position);
+        self.last_position.push(particle.last_position);
+        self.acceleration.push(particle.acceleration);
+        self.radius.push(particle.radius);
+        self.mass.push(particle.mass);
+        self.inventory.push(particle.inventory);
+        self.temperature.push(particle.temperature);
+        self.generation.push(particle.generation);
+        self.parent.push(particle.parent);
+        self.birth_time.push(particle.birth_time);
+        self.particle_type.push(particle.particle_type);
+        self.checked_position.push(particle.position);
+        self.slot.push(slot);
+
+        return ParticleId {
+            slot: slot,
+            generation: self.slot_generation[slot as usize],
+        }
+    }
+
+    // Remove a particle (swap-remove, so only the last particle changes index)
+    // Returns false if the handle was already stale
+    fn despawn(&mut self, id: ParticleId) -> bool {
+        let i = match self.index_of(id) {
+            Some(i) => i,
+            None => return false,
+        };
+
+        // Step 1: Swap-remove from every buffer
+        self.position.swap_remove(i);
+        self.last_position.swap_remove(i);
+        self.acceleration.swap_remove(i);
+        self.radius.swap_remove(i);
+        self.mass.swap_remove(i);
+        self.inventory.swap_remove(i);
+        self.temperature.swap_remove(i);
+        self.generation.swap_remove(i);
+        self.parent.swap_remove(i);
+        self.birth_time.swap_remove(i);
+        self.particle_type.swap_remove(i);
+        self.checked_position.swap_remove(i);
+        self.slot.swap_remove(i);
+
+        // Step 2: The old last particle now lives at i
+        if i < self.len() {
+            self.slot_index[self.slot[i] as usize] = i;
+        }
+
+        // Step 3: Retire the handle
+        free_slot(self, id.slot);
+        return true;
+    }
+
+    // Keep only the particles keep returns true for (preserving order)
+    fn retain<F: FnMut(&Particle) -> bool>(&mut self, mut keep: F) {
+        let mut kept = 0;
+        for i in 0..self.len() {
+            let particle = self.get(i);
+            let slot = self.slot[i];
+            let checked_position = self.checked_position[i];
+            if keep(&particle) {
+                self.position[kept] = particle.position;
+                self.last_position[kept] = particle.last_position;
+                self.acceleration[kept] = particle.acceleration;
+                self.radius[kept] = particle.radius;
+                self.mass[kept] = particle.mass;
+                self.inventory[kept] = particle.inventory;
+                self.temperature[kept] = particle.temperature;
+                self.generation[kept] = particle.generation;
+                self.parent[kept] = particle.parent;
+                self.birth_time[kept] = particle.birth_time;
+                self.particle_type[kept] = particle.particle_type;
+                self.checked_position[kept] = checked_position;
+                self.slot[kept] = slot;
+                self.slot_index[slot as usize] = kept;
+                kept += 1;
+            } else {
+                free_slot(self, slot);
+            }
+        }
+
+        self.position.truncate(kept);
+        self.last_position.truncate(kept);
+        self.acceleration.truncate(kept);
+        self.radius.truncate(kept);
+        self.mass.truncate(kept);
+        self.inventory.truncate(kept);
+        self.temperature.truncate(kept);
+        self.generation.truncate(kept);
+        self.parent.truncate(kept);
+        self.birth_time.truncate(kept);
+        self.particle_type.truncate(kept);
+        self.checked_position.truncate(kept);
+        self.slot.truncate(kept);
+    }
+}
+
+// Helper function to retire a slot (bumping its generation invalidates old handles)
+fn free_slot(particles: &mut Particles, slot: u32) {
+    let generation = &mut particles.slot_generation[slot as usize];
+    *generation = generation.wrapping_add(1);
+    particles.free_slots.push(slot);
+}
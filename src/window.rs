@@ -11,14 +11,17 @@ pub struct WindowSettings {
     pub title: String,
 }
 
+// OpenGL contexts to try, in order: (major, minor, profile, forward compatible)
+// (4.4 Compatibility runs the coolant in compute shaders; 3.3 Core can still draw it from the CPU)
+const CONTEXT_VERSIONS: [(u32, u32, glfw::OpenGlProfileHint, bool); 2] = [
+    (4, 4, glfw::OpenGlProfileHint::Compat, false),
+    (3, 3, glfw::OpenGlProfileHint::Core, true),
+];
+
 // Used to initialize GLFW at beginning of program
 pub fn init_glfw() -> glfw::Glfw {
     // Initialize GLFW
-    let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
-
-    // Set OpenGL hints (v4.4 Compatibility)
-    glfw.window_hint(glfw::WindowHint::ContextVersion(4,4));
-    glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Compat));
+    let glfw = glfw::init(report_glfw_error).unwrap();
 
     // Return GLFW instance
     return glfw;
@@ -26,9 +29,19 @@ pub fn init_glfw() -> glfw::Glfw {
 
 // Create a window
 pub fn create_window(glfw: &mut glfw::Glfw, settings: WindowSettings) -> (glfw::PWindow, glfw::GlfwReceiver<(f64, glfw::WindowEvent)>) {
-    // Create window    
-    let (mut window, events) = glfw.create_window(settings.width, settings.height, settings.title.as_str(), glfw::WindowMode::Windowed)
-        .expect("Failed to create GLFW window");
+    // Create window (with the first context the driver has)
+    let mut created = None;
+    for &(major, minor, profile, forward_compat) in CONTEXT_VERSIONS.iter() {
+        glfw.default_window_hints();
+        glfw.window_hint(glfw::WindowHint::ContextVersion(major, minor));
+        glfw.window_hint(glfw::WindowHint::OpenGlProfile(profile));
+        glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(forward_compat));
+        created = glfw.create_window(settings.width, settings.height, settings.title.as_str(), glfw::WindowMode::Windowed);
+        if created.is_some() {
+            break;
+        }
+    }
+    let (mut window, events) = created.expect("Failed to create GLFW window");
     
     // Set context to current
     window.make_current();
@@ -51,4 +64,15 @@ pub fn create_window(glfw: &mut glfw::Glfw, settings: WindowSettings) -> (glfw::
     }
 
     return (window, events);
-}
\ No newline at end of file
+}
+
+// Helper function to report GLFW errors
+// (a context version the driver lacks isn't fatal: create_window tries the next one)
+fn report_glfw_error(error: glfw::Error, description: String) {
+    match error {
+        glfw::Error::VersionUnavailable | glfw::Error::ApiUnavailable => {
+            println!("ERROR::GLFW::CONTEXT_UNAVAILABLE\n{}", description);
+        },
+        _ => glfw::fail_on_errors(error, description),
+    }
+}